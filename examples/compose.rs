@@ -0,0 +1,91 @@
+use bevy::{
+	app::{App, Startup, Update},
+	core_pipeline::core_2d::Camera2dBundle,
+	ecs::system::{Commands, Res, ResMut, Resource},
+	gizmos::gizmos::Gizmos,
+	input::{keyboard::KeyCode, ButtonInput},
+	math::{Rect, Vec2},
+	render::camera::ClearColor,
+	DefaultPlugins,
+};
+
+use itertools::Itertools;
+use rarc::{
+	geom::{arc_poly::ArcPoly, boolean::BoolOp, region::Region},
+	math::Tolerance,
+	palette::Palette,
+	raster::rasterize_polys,
+};
+
+/// A plate with shapes engraved into it and embossed on top, composed in a
+/// single pass. Space toggles between the operands and the result. With
+/// `--pbm` the result is printed as a PBM image instead, without a window.
+fn main() {
+	if std::env::args().any(|arg| arg == "--pbm") {
+		let polys = Region::compose(&plate(), &Tolerance::default())
+			.iter()
+			.flat_map(Region::loops)
+			.collect_vec();
+		print!("{}", rasterize_polys(&polys, 100, 50, 10.0).to_pbm());
+		return;
+	}
+	App::new()
+		.add_plugins(DefaultPlugins)
+		.insert_resource(ShowOperands(false))
+		.add_systems(Startup, setup)
+		.add_systems(Update, (hotkeys, draw))
+		.run();
+}
+
+#[derive(Resource)]
+struct ShowOperands(bool);
+
+fn setup(mut commands: Commands) {
+	commands.spawn(Camera2dBundle::default());
+}
+
+fn hotkeys(keys: Res<ButtonInput<KeyCode>>, mut show: ResMut<ShowOperands>) {
+	if keys.just_pressed(KeyCode::Space) {
+		show.0 = !show.0;
+	}
+}
+
+fn plate() -> Vec<(Region, BoolOp)> {
+	let region = |outer: ArcPoly| Region { outer, ..Default::default() };
+	let engraving = [Vec2::new(-150.0, 40.0), Vec2::new(-60.0, -40.0)];
+	let embossing = [Vec2::new(-150.0, 40.0), Vec2::new(120.0, 0.0)];
+	let mut operations = vec![(
+		region(ArcPoly::rectangle(Rect::new(-250.0, -120.0, 250.0, 120.0))),
+		BoolOp::Union,
+	)];
+	operations.extend(engraving.iter().map(|&center| {
+		(region(ArcPoly::circle(center, 50.0)), BoolOp::Difference)
+	}));
+	operations.extend(
+		embossing
+			.iter()
+			.map(|&center| (region(ArcPoly::circle(center, 25.0)), BoolOp::Union)),
+	);
+	operations.push((
+		region(ArcPoly::circle(Vec2::new(250.0, 120.0), 80.0)),
+		BoolOp::Difference,
+	));
+	operations
+}
+
+fn draw(show: Res<ShowOperands>, mut gizmos: Gizmos) {
+	let background = ClearColor::default().0;
+	let operations = plate();
+	if show.0 {
+		for (region, op) in &operations {
+			let color = Palette::OkabeIto.color_on(*op as usize, background);
+			region.outer.draw(&mut gizmos, &color);
+		}
+		return;
+	}
+	for region in Region::compose(&operations, &Tolerance::default()) {
+		for (i, poly) in region.loops().iter().enumerate() {
+			poly.draw(&mut gizmos, &Palette::OkabeIto.color_on(i, background));
+		}
+	}
+}
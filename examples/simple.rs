@@ -0,0 +1,6 @@
+use bevy::{app::App, DefaultPlugins};
+use rarc::plugin::SimpleDemoPlugin;
+
+fn main() {
+	App::new().add_plugins(DefaultPlugins).add_plugins(SimpleDemoPlugin).run();
+}
@@ -0,0 +1,84 @@
+use bevy::{
+	app::{App, Startup, Update},
+	core_pipeline::core_2d::Camera2dBundle,
+	ecs::system::{Commands, Res, ResMut, Resource},
+	gizmos::gizmos::Gizmos,
+	input::{keyboard::KeyCode, ButtonInput},
+	math::Vec2,
+	render::camera::ClearColor,
+	DefaultPlugins,
+};
+
+use rarc::{
+	geom::{
+		path::{ArcPath, StrokeParts},
+		segment::{SegEnd, Segment},
+	},
+	math::Tolerance,
+	palette::Palette,
+	raster::rasterize_polys,
+};
+
+/// Stroke of a wavy baseline, its parts drawn in separate colors. The arrow
+/// keys change the half width. With `--pbm` the stroke is printed as a PBM
+/// image instead, without a window.
+fn main() {
+	if std::env::args().any(|arg| arg == "--pbm") {
+		let path = baseline();
+		let outline = path.stroke(12.0, &Tolerance::default()).outline();
+		let mut raster = rasterize_polys(&[outline], 100, 30, 10.0);
+		raster.draw_path(&path);
+		print!("{}", raster.to_pbm());
+		return;
+	}
+	App::new()
+		.add_plugins(DefaultPlugins)
+		.insert_resource(HalfWidth(12.0))
+		.add_systems(Startup, setup)
+		.add_systems(Update, (hotkeys, draw))
+		.run();
+}
+
+#[derive(Resource)]
+struct HalfWidth(f32);
+
+fn setup(mut commands: Commands) {
+	commands.spawn(Camera2dBundle::default());
+}
+
+fn hotkeys(keys: Res<ButtonInput<KeyCode>>, mut half_width: ResMut<HalfWidth>) {
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		half_width.0 *= 1.25;
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		half_width.0 *= 0.8;
+	}
+}
+
+/// Alternating arcs through points along a line, like a handwritten
+/// baseline.
+fn baseline() -> ArcPath {
+	let mut path = ArcPath { segments: vec![], end: Vec2::new(-300.0, 0.0) };
+	let mut tangent = Vec2::new(1.0, 1.0);
+	for i in 0..6 {
+		let end =
+			path.end + Vec2::new(100.0, if i % 2 == 0 { 20.0 } else { -20.0 });
+		let segment = Segment::from_start_tangent_end(path.end, tangent, end);
+		tangent = segment.tangent_at(SegEnd::At(end), &end);
+		path.segments.push(segment);
+		path.end = end;
+	}
+	path
+}
+
+fn draw(half_width: Res<HalfWidth>, mut gizmos: Gizmos) {
+	let background = ClearColor::default().0;
+	let path = baseline();
+	let StrokeParts { left, right, start_cap, end_cap } =
+		path.stroke(half_width.0, &Tolerance::default());
+	for (i, part) in
+		[&path, &left, &right, &start_cap, &end_cap].iter().enumerate()
+	{
+		part.draw(&mut gizmos, &Palette::OkabeIto.color_on(i, background));
+	}
+}
@@ -0,0 +1,373 @@
+use bevy::{
+	ecs::system::Resource, gizmos::gizmos::Gizmos, math::Vec2, reflect::Reflect,
+	render::color::Color,
+};
+use derive_more::Display;
+
+use crate::{
+	geom::{
+		arc_poly::{ArcPoly, SegIdx, ShrinkError},
+		boolean::{self, BoolOp},
+	},
+	math::{Tolerance, Transform2, Xform2},
+};
+
+#[derive(
+	Clone,
+	Copy,
+	Debug,
+	Default,
+	Display,
+	Reflect,
+	PartialEq,
+	Eq,
+	Hash,
+	PartialOrd,
+	Ord,
+)]
+#[display(fmt = "shape({})", _0)]
+pub struct ShapeId(pub u32);
+
+#[derive(Clone, Reflect)]
+pub struct Shape {
+	pub id: ShapeId,
+	pub poly: ArcPoly,
+}
+
+#[derive(Clone, Reflect)]
+pub struct Layer {
+	pub name: String,
+	pub visible: bool,
+	pub color: Color,
+	pub shapes: Vec<Shape>,
+}
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum HitKind {
+	Boundary,
+	Inside,
+}
+
+#[derive(Clone, Copy, Display)]
+#[display(fmt = "hit({}, {}, {}, {})", layer, shape, segment_idx, kind)]
+pub struct HitInfo {
+	pub layer: usize,
+	pub shape: ShapeId,
+	/// The segment closest to the hit point.
+	pub segment_idx: SegIdx,
+	pub kind: HitKind,
+}
+
+#[derive(Clone, Reflect)]
+pub enum EditOp {
+	AddShape {
+		layer: usize,
+		poly: ArcPoly,
+	},
+	RemoveShape(ShapeId),
+	Transform {
+		id: ShapeId,
+		xform: Xform2,
+	},
+	/// Replaces the shape by its inward offset, which may be several shapes.
+	Offset {
+		id: ShapeId,
+		amount: f32,
+		tolerance: Tolerance,
+	},
+	/// Replaces the shape by its combination with `other` by `op`, which
+	/// may be several shapes, holes among them, or none. `other` is used up.
+	Boolean {
+		id: ShapeId,
+		other: ShapeId,
+		op: BoolOp,
+		tolerance: Tolerance,
+	},
+}
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum EditError {
+	#[display(fmt = "unknown layer {}", _0)]
+	UnknownLayer(usize),
+	#[display(fmt = "unknown {}", _0)]
+	UnknownShape(ShapeId),
+	#[display(fmt = "offset failed: {}", _0)]
+	Offset(ShrinkError),
+}
+
+/// Named layers of shapes, drawn in order so later layers end up on top.
+/// Registering the type lets bevy's reflection serialize it.
+#[derive(Clone, Default, Reflect, Resource)]
+pub struct Document {
+	pub layers: Vec<Layer>,
+	next_id: u32,
+	/// Operations with the state before them.
+	#[reflect(ignore)]
+	undo_stack: Vec<(EditOp, Snapshot)>,
+	/// Undone operations with the state after them.
+	#[reflect(ignore)]
+	redo_stack: Vec<(EditOp, Snapshot)>,
+}
+
+/// The shapes of a `Document` along with the next id to assign, so that
+/// shapes added again by `redo` get the ids later operations refer to.
+#[derive(Clone)]
+struct Snapshot {
+	layers: Vec<Layer>,
+	next_id: u32,
+}
+
+impl Document {
+	pub fn add_layer(&mut self, name: &str, color: Color) -> usize {
+		self.layers.push(Layer {
+			name: name.to_string(),
+			visible: true,
+			color,
+			shapes: vec![],
+		});
+		self.layers.len() - 1
+	}
+
+	pub fn layer_index(&self, name: &str) -> Option<usize> {
+		self.layers.iter().position(|layer| layer.name == name)
+	}
+
+	pub fn add_shape(&mut self, layer: usize, poly: ArcPoly) -> ShapeId {
+		let id = ShapeId(self.next_id);
+		self.next_id += 1;
+		self.layers[layer].shapes.push(Shape { id, poly });
+		id
+	}
+
+	pub fn shape(&self, id: ShapeId) -> Option<&Shape> {
+		self.layers.iter().flat_map(|layer| &layer.shapes).find(|s| s.id == id)
+	}
+
+	pub fn shape_mut(&mut self, id: ShapeId) -> Option<&mut Shape> {
+		self
+			.layers
+			.iter_mut()
+			.flat_map(|layer| &mut layer.shapes)
+			.find(|s| s.id == id)
+	}
+
+	pub fn remove_shape(&mut self, id: ShapeId) -> Option<ArcPoly> {
+		for layer in self.layers.iter_mut() {
+			if let Some(i) = layer.shapes.iter().position(|s| s.id == id) {
+				return Some(layer.shapes.remove(i).poly);
+			}
+		}
+		None
+	}
+
+	pub fn clear_layer(&mut self, layer: usize) {
+		self.layers[layer].shapes.clear();
+	}
+
+	/// The topmost visible shape whose boundary lies within `tolerance` of
+	/// `point` or which contains it.
+	pub fn hit_test(&self, point: Vec2, tolerance: f32) -> Option<HitInfo> {
+		for (layer_idx, layer) in self.layers.iter().enumerate().rev() {
+			if !layer.visible {
+				continue;
+			}
+			for shape in layer.shapes.iter().rev() {
+				let Some((segment_idx, closest)) = shape.poly.closest_point(&point)
+				else {
+					continue;
+				};
+				let kind = if (closest - point).length() <= tolerance {
+					HitKind::Boundary
+				} else if shape.poly.contains_point(&point) {
+					HitKind::Inside
+				} else {
+					continue;
+				};
+				return Some(HitInfo {
+					layer: layer_idx,
+					shape: shape.id,
+					segment_idx,
+					kind,
+				});
+			}
+		}
+		None
+	}
+
+	pub fn draw(&self, gizmos: &mut Gizmos) {
+		for layer in self.layers.iter().filter(|layer| layer.visible) {
+			for shape in layer.shapes.iter() {
+				shape.poly.draw(gizmos, &layer.color);
+			}
+		}
+	}
+
+	/// Applies `op`, recording it for `undo` and dropping the redo history.
+	pub fn apply(&mut self, op: EditOp) -> Result<(), EditError> {
+		self.execute(op)?;
+		self.redo_stack.clear();
+		Ok(())
+	}
+
+	/// Reverts the last applied operation, returning false if there is none.
+	pub fn undo(&mut self) -> bool {
+		match self.undo_stack.pop() {
+			Some((op, before)) => {
+				let after = self.restore(before);
+				self.redo_stack.push((op, after));
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Reapplies the last undone operation, returning false if there is none.
+	/// The state it left is restored rather than the operation executed
+	/// again, so it cannot fail or differ from the first time.
+	pub fn redo(&mut self) -> bool {
+		match self.redo_stack.pop() {
+			Some((op, after)) => {
+				let before = self.restore(after);
+				self.undo_stack.push((op, before));
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn snapshot(&self) -> Snapshot {
+		Snapshot { layers: self.layers.clone(), next_id: self.next_id }
+	}
+
+	/// Replaces the state by `snapshot`, returning the previous one.
+	fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+		let previous = self.snapshot();
+		self.layers = snapshot.layers;
+		self.next_id = snapshot.next_id;
+		previous
+	}
+
+	/// Index of the layer holding the shape `id`.
+	fn layer_of(&self, id: ShapeId) -> Result<usize, EditError> {
+		self
+			.layers
+			.iter()
+			.position(|layer| layer.shapes.iter().any(|s| s.id == id))
+			.ok_or(EditError::UnknownShape(id))
+	}
+
+	fn execute(&mut self, op: EditOp) -> Result<(), EditError> {
+		let before = self.snapshot();
+		match &op {
+			EditOp::AddShape { layer, poly } => {
+				if *layer >= self.layers.len() {
+					return Err(EditError::UnknownLayer(*layer));
+				}
+				self.add_shape(*layer, poly.clone());
+			}
+			EditOp::RemoveShape(id) => {
+				self.remove_shape(*id).ok_or(EditError::UnknownShape(*id))?;
+			}
+			EditOp::Transform { id, xform } => {
+				let shape = self.shape_mut(*id).ok_or(EditError::UnknownShape(*id))?;
+				shape.poly.apply_xform(xform);
+			}
+			EditOp::Offset { id, amount, tolerance } => {
+				let layer = self.layer_of(*id)?;
+				let shrunk = self
+					.shape(*id)
+					.unwrap()
+					.poly
+					.shrunk(*amount, tolerance)
+					.map_err(EditError::Offset)?;
+				self.remove_shape(*id);
+				for sub_poly in shrunk {
+					self.add_shape(layer, sub_poly);
+				}
+			}
+			EditOp::Boolean { id, other, op, tolerance } => {
+				let layer = self.layer_of(*id)?;
+				let poly = |id: ShapeId| {
+					self
+						.shape(id)
+						.map(|s| [s.poly.clone()])
+						.ok_or(EditError::UnknownShape(id))
+				};
+				let (a, b) = (poly(*id)?, poly(*other)?);
+				let combined =
+					boolean::compose(&[(&a, BoolOp::Union), (&b, *op)], tolerance);
+				self.remove_shape(*id);
+				self.remove_shape(*other);
+				for poly in combined {
+					self.add_shape(layer, poly);
+				}
+			}
+		}
+		self.undo_stack.push((op, before));
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::math::Rect;
+
+	use super::*;
+
+	#[test]
+	fn redo_keeps_ids_for_later_operations() {
+		let mut document = Document::default();
+		let layer = document.add_layer("shapes", Color::WHITE);
+		let poly = ArcPoly::circle(Vec2::ZERO, 10.0);
+		document.apply(EditOp::AddShape { layer, poly }).unwrap();
+		let id = document.layers[layer].shapes[0].id;
+		let xform = Xform2::from_translation(Vec2::X);
+		document.apply(EditOp::Transform { id, xform }).unwrap();
+		assert!(document.undo() && document.undo());
+		assert!(document.layers[layer].shapes.is_empty());
+		assert!(document.redo() && document.redo());
+		assert!(!document.redo());
+		let shape = &document.layers[layer].shapes[0];
+		assert_eq!(shape.id, id);
+		assert_eq!(shape.poly.segments[0].center, Vec2::X);
+		let other = document.add_shape(layer, ArcPoly::circle(Vec2::ZERO, 1.0));
+		assert_ne!(other, id);
+	}
+
+	#[test]
+	fn failed_offset_keeps_the_shape() {
+		let mut document = Document::default();
+		let layer = document.add_layer("shapes", Color::WHITE);
+		let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+		let id = document.add_shape(layer, ArcPoly::rectangle(rect));
+		let op =
+			EditOp::Offset { id, amount: 1.0, tolerance: Tolerance::default() };
+		assert!(matches!(document.apply(op), Err(EditError::Offset(_))));
+		assert!(document.shape(id).is_some());
+		assert!(!document.undo());
+	}
+
+	#[test]
+	fn boolean_replaces_both_shapes_until_undone() {
+		let mut document = Document::default();
+		let layer = document.add_layer("shapes", Color::WHITE);
+		let plate = ArcPoly::rectangle(Rect::new(0.0, 0.0, 100.0, 50.0));
+		let id = document.add_shape(layer, plate);
+		let other = document.add_shape(layer, ArcPoly::circle(Vec2::ZERO, 20.0));
+		let tolerance = Tolerance::default();
+		let op = BoolOp::Difference;
+		document.apply(EditOp::Boolean { id, other, op, tolerance }).unwrap();
+		let shapes = &document.layers[layer].shapes;
+		assert_eq!(shapes.len(), 1);
+		let expected = 5000.0 - std::f32::consts::PI * 100.0;
+		assert!((shapes[0].poly.area() - expected).abs() < 1e-3 * expected);
+		assert!(document.shape(id).is_none() && document.shape(other).is_none());
+		assert!(document.undo());
+		assert_eq!(document.layers[layer].shapes.len(), 2);
+		assert!(document.shape(id).is_some() && document.shape(other).is_some());
+		let missing = ShapeId(99);
+		let op = EditOp::Boolean { id, other: missing, op, tolerance };
+		assert_eq!(document.apply(op), Err(EditError::UnknownShape(missing)));
+		assert_eq!(document.layers[layer].shapes.len(), 2);
+	}
+}
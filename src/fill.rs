@@ -0,0 +1,158 @@
+use bevy::math::Vec2;
+use itertools::Itertools;
+
+use crate::{
+	geom::{
+		arc_poly::{ArcPoly, ShrinkError},
+		boolean,
+		path::ArcPath,
+		segment::Segment,
+	},
+	math::Tolerance,
+};
+
+/// Parallel lines `spacing` apart at `angle` to the x axis, clipped to the
+/// region bounded by `loops` as in `boolean`. Each piece inside the region is
+/// a path of a single straight segment, ordered line by line and along each
+/// line. Clipping is exact against the arcs; pieces shorter than
+/// `tolerance.merge_distance`, e.g. where a line grazes the boundary, are
+/// dropped. A `spacing` which is not positive yields no lines.
+pub fn hatch(
+	loops: &[ArcPoly],
+	spacing: f32,
+	angle: f32,
+	tolerance: &Tolerance,
+) -> Vec<ArcPath> {
+	if spacing.is_nan() || spacing <= 0.0 {
+		return vec![];
+	}
+	let dir = Vec2::from_angle(angle);
+	let normal = dir.perp();
+	let corners = loops
+		.iter()
+		.map(|l| l.bounds())
+		.flat_map(|r| {
+			[r.min, r.max, Vec2::new(r.min.x, r.max.y), Vec2::new(r.max.x, r.min.y)]
+		})
+		.collect_vec();
+	let Some((low, high)) =
+		corners.iter().map(|c| c.dot(normal)).minmax().into_option()
+	else {
+		return vec![];
+	};
+	let mut paths = vec![];
+	// Lines are counted rather than stepped to, which far from the origin
+	// would round to the same offset over and over.
+	let first = (low / spacing).ceil() * spacing;
+	let count = ((high - first) / spacing).floor() + 1.0;
+	for k in 0..count.max(0.0) as usize {
+		let offset = first + k as f32 * spacing;
+		let origin = offset * normal;
+		let params = loops
+			.iter()
+			.flat_map(|l| {
+				l.indices()
+					.flat_map(move |i| l[i].intersect_line(l.end_of(i), &origin, &dir))
+			})
+			.map(|p| (p - origin).dot(dir))
+			.sorted_by(f32::total_cmp)
+			.dedup_by(|s, t| (*s - *t).abs() < tolerance.merge_distance)
+			.collect_vec();
+		let mut pieces: Vec<(f32, f32)> = vec![];
+		for (s, t) in params.into_iter().tuple_windows() {
+			if !boolean::contains_point(loops, &(origin + 0.5 * (s + t) * dir)) {
+				continue;
+			}
+			// Pieces meeting where the line passes a vertex or a crossing of
+			// the boundary continue each other.
+			match pieces.last_mut() {
+				Some(last) if last.1 == s => last.1 = t,
+				_ => pieces.push((s, t)),
+			}
+		}
+		paths.extend(pieces.into_iter().map(|(s, t)| {
+			let (start, end) = (origin + s * dir, origin + t * dir);
+			ArcPath { segments: vec![Segment::straight(start, end)], end }
+		}));
+	}
+	paths
+}
+
+/// Offsets of `poly` by `spacing`, `2 * spacing`, ... until nothing is left,
+/// innermost last, as concentric infill following the boundary. Fails for
+/// loops `ArcPoly::shrunk` cannot offset and for a positive `spacing` within
+/// `tolerance.merge_distance`, see `ArcPoly::shrunk_concentric`. A `spacing`
+/// which is not positive yields no offsets.
+pub fn concentric(
+	poly: &ArcPoly,
+	spacing: f32,
+	tolerance: &Tolerance,
+) -> Result<Vec<ArcPoly>, ShrinkError> {
+	if spacing.is_nan() || spacing <= 0.0 {
+		return Ok(vec![]);
+	}
+	let rings = poly.shrunk_concentric(spacing, usize::MAX, tolerance)?;
+	Ok(rings.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::math::Rect;
+
+	use super::*;
+
+	fn square(min: f32, size: f32) -> Vec<ArcPoly> {
+		vec![ArcPoly::rectangle(Rect::new(min, min, min + size, min + size))]
+	}
+
+	#[test]
+	fn hatch_rejects_non_positive_spacing() {
+		let tolerance = Tolerance::default();
+		for spacing in [0.0, -1.0, f32::NAN] {
+			assert!(hatch(&square(0.0, 10.0), spacing, 0.0, &tolerance).is_empty());
+		}
+	}
+
+	#[test]
+	fn hatch_far_from_origin() {
+		// Adding the spacing to offsets around 1e8 would not change them.
+		let paths = hatch(&square(1e8, 1e3), 2.0, 0.0, &Tolerance::default());
+		assert!((400..=510).contains(&paths.len()), "{}", paths.len());
+	}
+
+	#[test]
+	fn concentric_of_unsupported_loop_fails() {
+		assert!(
+			concentric(&square(0.0, 10.0)[0], 1.0, &Tolerance::default()).is_err()
+		);
+		assert!(concentric(
+			&ArcPoly::circle(Vec2::ZERO, 10.0),
+			0.0,
+			&Tolerance::default()
+		)
+		.unwrap()
+		.is_empty());
+		assert_eq!(
+			concentric(
+				&ArcPoly::circle(Vec2::ZERO, 10.0),
+				3.0,
+				&Tolerance::default()
+			)
+			.unwrap()
+			.len(),
+			3
+		);
+	}
+
+	#[test]
+	fn concentric_rejects_spacings_that_do_not_shrink() {
+		let tolerance = Tolerance::default();
+		let circle = ArcPoly::circle(Vec2::ZERO, 10.0);
+		for spacing in [1e-4, tolerance.merge_distance] {
+			assert!(matches!(
+				concentric(&circle, spacing, &tolerance),
+				Err(ShrinkError::InvalidAmount(_))
+			));
+		}
+	}
+}
@@ -0,0 +1,346 @@
+use bevy::{math::Vec2, render::color::Color};
+use derive_more::Display;
+use itertools::Itertools;
+
+use crate::{
+	document::Document,
+	geom::{
+		arc_poly::ArcPoly,
+		segment::{ArcAttributes, Bend, Segment},
+	},
+};
+
+/// Version of the plain text interchange format for polygons and documents,
+/// conventionally stored in `.rarc` files. The first line is a header
+/// `rarc <version>`, followed by one record per line:
+///
+/// ```text
+/// layer <visible> <r> <g> <b> <a> <name>
+/// shape
+/// seg <bend> <initial x> <initial y> <center x> <center y> <layer> <feed> <power>
+/// ```
+///
+/// `shape` starts a new polygon, in the latest layer when reading a document,
+/// and `seg` appends a segment to it. `bend` is one of `inward`, `outward`
+/// and `straight` and an absent feed or power is written as `-`. A layer
+/// `name` is a single field in which `%`, whitespace and control characters
+/// are percent-encoded as their UTF-8 bytes, and an empty name is written as
+/// `-`. Version 1 wrote the name as it is, taking up the remaining fields,
+/// which is how it is still read from such files. Empty lines and lines
+/// starting with `#` are ignored. Later versions only add records and
+/// trailing fields, so readers skip records they do not know and fields
+/// beyond those they read.
+pub const FORMAT_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum FormatError {
+	#[display(fmt = "missing rarc header")]
+	MissingHeader,
+	/// The 1-based line number of the offending record.
+	#[display(fmt = "malformed record on line {}", _0)]
+	Malformed(usize),
+	#[display(fmt = "segment before any shape on line {}", _0)]
+	NoShape(usize),
+}
+
+pub fn write_polys(polys: &[ArcPoly]) -> String {
+	let mut text = header();
+	for poly in polys {
+		write_poly(&mut text, poly);
+	}
+	text
+}
+
+pub fn read_polys(text: &str) -> Result<Vec<ArcPoly>, FormatError> {
+	let mut polys: Vec<ArcPoly> = vec![];
+	let (_, records) = records(text)?;
+	for (line, record) in records {
+		match record.as_slice() {
+			["shape", ..] => polys.push(ArcPoly::default()),
+			["seg", fields @ ..] => polys
+				.last_mut()
+				.ok_or(FormatError::NoShape(line))?
+				.segments
+				.push(parse_segment(fields).ok_or(FormatError::Malformed(line))?),
+			_ => {}
+		}
+	}
+	Ok(polys)
+}
+
+/// Layers with their shapes. Shape ids are not stored, the shapes get fresh
+/// ids when read.
+pub fn write_document(document: &Document) -> String {
+	let mut text = header();
+	for layer in document.layers.iter() {
+		let [r, g, b, a] = layer.color.as_rgba_f32();
+		text += &format!(
+			"layer {} {} {} {} {} {}\n",
+			layer.visible as u8,
+			r,
+			g,
+			b,
+			a,
+			encode_name(&layer.name)
+		);
+		for shape in layer.shapes.iter() {
+			write_poly(&mut text, &shape.poly);
+		}
+	}
+	text
+}
+
+pub fn read_document(text: &str) -> Result<Document, FormatError> {
+	let mut document = Document::default();
+	let mut shape: Option<ArcPoly> = None;
+	let (version, records) = records(text)?;
+	for (line, record) in records {
+		match record.as_slice() {
+			["layer", visible, r, g, b, a, name @ ..] => {
+				finish_shape(&mut document, &mut shape);
+				let parse = |s: &str| s.parse::<f32>().ok();
+				let name = match (version, name) {
+					(1, _) => Some(name.join(" ")),
+					(_, [name, ..]) => decode_name(name),
+					_ => None,
+				};
+				let (Some(r), Some(g), Some(b), Some(a), Some(name)) =
+					(parse(r), parse(g), parse(b), parse(a), name)
+				else {
+					return Err(FormatError::Malformed(line));
+				};
+				let layer = document.add_layer(&name, Color::rgba(r, g, b, a));
+				document.layers[layer].visible = *visible != "0";
+			}
+			["shape", ..] => {
+				finish_shape(&mut document, &mut shape);
+				if document.layers.is_empty() {
+					return Err(FormatError::Malformed(line));
+				}
+				shape = Some(ArcPoly::default());
+			}
+			["seg", fields @ ..] => shape
+				.as_mut()
+				.ok_or(FormatError::NoShape(line))?
+				.segments
+				.push(parse_segment(fields).ok_or(FormatError::Malformed(line))?),
+			_ => {}
+		}
+	}
+	finish_shape(&mut document, &mut shape);
+	Ok(document)
+}
+
+fn finish_shape(document: &mut Document, shape: &mut Option<ArcPoly>) {
+	if let Some(poly) = shape.take() {
+		let layer = document.layers.len() - 1;
+		document.add_shape(layer, poly);
+	}
+}
+
+fn header() -> String {
+	format!("rarc {}\n", FORMAT_VERSION)
+}
+
+/// Whitespace separated fields of a record with its line number.
+type Record<'a> = (usize, Vec<&'a str>);
+
+/// The version from the header and the records. Any version is accepted,
+/// see `FORMAT_VERSION`.
+fn records(text: &str) -> Result<(u32, Vec<Record<'_>>), FormatError> {
+	let mut lines = text
+		.lines()
+		.enumerate()
+		.map(|(i, line)| (i + 1, line.split_whitespace().collect_vec()))
+		.filter(|(_, fields)| fields.first().is_some_and(|f| !f.starts_with('#')));
+	match lines.next() {
+		Some((_, header)) if header.len() >= 2 && header[0] == "rarc" => {
+			let version =
+				header[1].parse().map_err(|_| FormatError::MissingHeader)?;
+			Ok((version, lines.collect()))
+		}
+		_ => Err(FormatError::MissingHeader),
+	}
+}
+
+/// `name` as a single field, see `FORMAT_VERSION`.
+fn encode_name(name: &str) -> String {
+	match name {
+		"" => return "-".into(),
+		"-" => return "%2D".into(),
+		_ => {}
+	}
+	let mut field = String::new();
+	for c in name.chars() {
+		if c == '%' || c.is_whitespace() || c.is_control() {
+			for byte in c.to_string().bytes() {
+				field += &format!("%{:02X}", byte);
+			}
+		} else {
+			field.push(c);
+		}
+	}
+	field
+}
+
+fn decode_name(field: &str) -> Option<String> {
+	if field == "-" {
+		return Some(String::new());
+	}
+	let mut bytes = vec![];
+	let mut rest = field.as_bytes();
+	while let Some((&byte, tail)) = rest.split_first() {
+		rest = tail;
+		if byte != b'%' {
+			bytes.push(byte);
+			continue;
+		}
+		let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+		bytes.push(u8::from_str_radix(hex, 16).ok()?);
+		rest = &rest[2..];
+	}
+	String::from_utf8(bytes).ok()
+}
+
+fn write_poly(text: &mut String, poly: &ArcPoly) {
+	*text += "shape\n";
+	for segment in poly.segments.iter() {
+		let bend = match segment.bend {
+			Bend::Inward => "inward",
+			Bend::Outward => "outward",
+			Bend::Straight => "straight",
+		};
+		let optional =
+			|value: Option<f32>| value.map_or("-".into(), |v| v.to_string());
+		let ArcAttributes { layer, feed, power } = segment.attributes;
+		*text += &format!(
+			"seg {} {} {} {} {} {} {} {}\n",
+			bend,
+			segment.initial.x,
+			segment.initial.y,
+			segment.center.x,
+			segment.center.y,
+			layer,
+			optional(feed),
+			optional(power)
+		);
+	}
+}
+
+fn parse_segment(fields: &[&str]) -> Option<Segment> {
+	let [bend, ix, iy, cx, cy, layer, feed, power, ..] = fields else {
+		return None;
+	};
+	let bend = match *bend {
+		"inward" => Bend::Inward,
+		"outward" => Bend::Outward,
+		"straight" => Bend::Straight,
+		_ => return None,
+	};
+	let optional = |s: &str| match s {
+		"-" => Some(None),
+		_ => s.parse().ok().map(Some),
+	};
+	Some(Segment {
+		initial: Vec2::new(ix.parse().ok()?, iy.parse().ok()?),
+		center: Vec2::new(cx.parse().ok()?, cy.parse().ok()?),
+		bend,
+		attributes: ArcAttributes {
+			layer: layer.parse().ok()?,
+			feed: optional(feed)?,
+			power: optional(power)?,
+		},
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::{math::Rect, prelude::default};
+
+	use super::*;
+	use crate::{document::Layer, geom::arc_poly::ArcPolyGenInput};
+
+	fn assert_same_polys(found: &[ArcPoly], expected: &[ArcPoly]) {
+		assert_eq!(found.len(), expected.len());
+		for (found, expected) in found.iter().zip(expected) {
+			assert_eq!(found.segments.len(), expected.segments.len());
+			for (a, b) in found.segments.iter().zip(&expected.segments) {
+				assert_eq!(a.initial, b.initial);
+				assert_eq!(a.center, b.center);
+				assert_eq!(a.bend, b.bend);
+				assert!(a.attributes == b.attributes);
+			}
+		}
+	}
+
+	#[test]
+	fn polys_round_trip() {
+		let mut rectangle = ArcPoly::rectangle(Rect::new(-1.5, 0.0, 1e6, 1e-7));
+		rectangle.segments[1].attributes =
+			ArcAttributes { layer: 3, feed: Some(1200.0), power: None };
+		let polys = [
+			ArcPoly::from_gen_input(&ArcPolyGenInput::default()),
+			rectangle,
+			ArcPoly::default(),
+		];
+		let read = read_polys(&write_polys(&polys)).unwrap();
+		assert_same_polys(&read, &polys);
+	}
+
+	#[test]
+	fn documents_round_trip() {
+		let mut document = Document::default();
+		let cut =
+			document.add_layer("cut outline", Color::rgba(1.0, 0.5, 0.0, 1.0));
+		let engrave =
+			document.add_layer("engrave", Color::rgba(0.0, 0.0, 1.0, 0.25));
+		document.layers[engrave].visible = false;
+		document.add_shape(cut, ArcPoly::circle(Vec2::ZERO, 10.0));
+		document.add_shape(cut, ArcPoly::circle(Vec2::ONE, 0.1));
+		document.add_shape(engrave, ArcPoly::from_gen_input(&default()));
+		let read = read_document(&write_document(&document)).unwrap();
+		assert_eq!(read.layers.len(), document.layers.len());
+		for (found, expected) in read.layers.iter().zip(&document.layers) {
+			assert_eq!(found.name, expected.name);
+			assert_eq!(found.visible, expected.visible);
+			assert_eq!(found.color, expected.color);
+			let polys = |layer: &Layer| {
+				layer.shapes.iter().map(|shape| shape.poly.clone()).collect_vec()
+			};
+			assert_same_polys(&polys(found), &polys(expected));
+		}
+	}
+
+	#[test]
+	fn layer_names_are_single_fields() {
+		let mut document = Document::default();
+		for name in ["", "-", "%41", " cut  100%\touter ", "ünï\u{a0}code"] {
+			document.add_layer(name, Color::WHITE);
+		}
+		let text = write_document(&document);
+		let names = |document: &Document| {
+			document.layers.iter().map(|layer| layer.name.clone()).collect_vec()
+		};
+		let read = read_document(&text).unwrap();
+		assert_eq!(names(&read), names(&document));
+		// Fields added after the name by later versions are not part of it.
+		let text = text.replace('\n', " extra\n").replace("rarc 2 extra", "rarc 3");
+		assert_eq!(names(&read_document(&text).unwrap()), names(&document));
+		let read = read_document("rarc 1\nlayer 1 1 1 1 1 cut  outline\n");
+		assert_eq!(read.unwrap().layers[0].name, "cut outline");
+	}
+
+	#[test]
+	fn later_versions_read_as_far_as_known() {
+		let text = "# comment\nrarc 2\nshape closed\n\
+			seg outward 1 0 0 0 0 - - extra\nnote ignored\n";
+		let polys = read_polys(text).unwrap();
+		assert_eq!(polys.len(), 1);
+		assert_eq!(polys[0].segments[0].initial, Vec2::X);
+		assert!(matches!(read_polys("shape\n"), Err(FormatError::MissingHeader)));
+		assert!(matches!(
+			read_polys("rarc 1\nseg inward 0 0 1 1 0 - -"),
+			Err(FormatError::NoShape(2))
+		));
+	}
+}
@@ -1,16 +1,20 @@
 use std::{
 	f32::consts::PI,
+	f64::consts::TAU,
 	fmt::{Display, Formatter, Result},
+	ops::Index,
 };
 
 use bevy::{
 	ecs::{component::Component, system::Resource},
 	gizmos::gizmos::Gizmos,
-	math::Vec2,
+	math::{Rect, Vec2},
 	prelude::default,
 	reflect::Reflect,
 	render::color::Color,
+	tasks::{AsyncComputeTaskPool, ComputeTaskPool, ParallelSlice, Task},
 };
+use derive_more::Display;
 use itertools::Itertools;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use rand_distr::{Distribution, UnitDisc};
@@ -18,12 +22,21 @@ use rand_distr::{Distribution, UnitDisc};
 use crate::{
 	geom::segment::CollisionType,
 	math::{
-		angle_counter_clockwise, bool_to_sign, circle_center_from_3_points,
-		midpoint, three_circle_collision, two_circle_collision, FloatVec2,
+		angle_counter_clockwise, bool_to_sign, checked,
+		circle_center_from_3_points, midpoint, min_enclosing_circle, ApproxEq,
+		Circle, CircleIntersection, FloatBackend, FloatVec2, IntersectionBackend,
+		Tolerance, Transform2, Xform2,
 	},
+	util::{Cancellation, Param},
 };
 
-use super::segment::{draw_segment, Bend, Collision, Segment};
+use super::boolean;
+use super::grid::{Grid, SampleKind};
+use super::index::ArcBvh;
+use super::segment::{
+	draw_segment, Bend, Collision, Contact, InvalidArc, SegEnd, Segment,
+	SegmentSnapshot, Side,
+};
 
 #[derive(Component, Reflect, Default, Clone)]
 pub struct ArcPoly {
@@ -40,58 +53,1006 @@ impl Display for ArcPoly {
 	}
 }
 
+impl Index<SegIdx> for ArcPoly {
+	type Output = Segment;
+
+	fn index(&self, idx: SegIdx) -> &Segment {
+		&self.segments[idx.0]
+	}
+}
+
+/// Index of a segment of an `ArcPoly`. The neighbours of a segment are found
+/// with `next` and `prev`, which wrap around the loop, rather than by
+/// modular arithmetic at every use.
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "{}", _0)]
+pub struct SegIdx(pub usize);
+
+impl SegIdx {
+	/// Index of the following segment in a loop of `n` segments.
+	pub fn next(self, n: usize) -> SegIdx {
+		SegIdx((self.0 + 1) % n)
+	}
+
+	/// Index of the preceding segment in a loop of `n` segments.
+	pub fn prev(self, n: usize) -> SegIdx {
+		SegIdx((self.0 + n - 1) % n)
+	}
+}
+
+/// Index of a loop among several, e.g. the results of an offset or the paths
+/// ordered by `toolpath::order_paths`.
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq, Hash)]
+#[display(fmt = "{}", _0)]
+pub struct LoopIdx(pub usize);
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum StartPosition {
+	/// The boundary point closest to the given point.
+	#[display(fmt = "point({})", _0)]
+	Point(Vec2),
+	/// The given arc length along the boundary from the current start.
+	#[display(fmt = "length({})", _0)]
+	Length(f32),
+}
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum Convexity {
+	Convex,
+	Concave,
+}
+
+/// Direction in which a loop travels around its interior.
+#[derive(Clone, Copy, Default, Display, Reflect, PartialEq)]
+pub enum Orientation {
+	#[default]
+	CounterClockwise,
+	Clockwise,
+}
+
+impl Orientation {
+	pub fn opposite(&self) -> Orientation {
+		match self {
+			Orientation::CounterClockwise => Orientation::Clockwise,
+			Orientation::Clockwise => Orientation::CounterClockwise,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum Containment {
+	Inside,
+	Outside,
+	/// Within `Tolerance::boundary_distance` of the boundary.
+	OnBoundary,
+}
+
+#[derive(Clone, Copy, Display)]
+#[display(fmt = "corner({}, {}, {}, {})", idx, place, turning_angle, convexity)]
+pub struct CornerInfo {
+	/// Index of the segment starting at the corner.
+	pub idx: SegIdx,
+	pub place: Vec2,
+	/// Signed angle from the incoming to the outgoing tangent, positive for
+	/// left turns.
+	pub turning_angle: f32,
+	pub convexity: Convexity,
+}
+
+/// Problem found by `ArcPoly::diagnose` or `Region::diagnose`.
+#[derive(Clone, Copy, Display, PartialEq)]
+pub enum ValidationIssue {
+	#[display(fmt = "empty loop")]
+	Empty,
+	#[display(fmt = "segment {}: {}", _0, _1)]
+	InvalidSegment(SegIdx, InvalidArc),
+	/// The arc ends farther than `Tolerance::merge_distance` off its circle,
+	/// which `repair_joints` fixes.
+	#[display(fmt = "segment {} ends {} off its circle", idx, drift)]
+	EndOffCircle { idx: SegIdx, drift: f32 },
+	/// Two segments cross other than at a joint they share.
+	#[display(fmt = "segments {} and {} cross at {}", first, second, point)]
+	Crossing { first: SegIdx, second: SegIdx, point: Vec2 },
+	/// A loop of a region runs the wrong way relative to its outer boundary.
+	#[display(fmt = "orientation {}, expected {}", found, expected)]
+	WrongOrientation { found: Orientation, expected: Orientation },
+}
+
+/// Why `ArcPoly::shrunk_with` gave no offset.
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum ShrinkError {
+	#[display(fmt = "cancelled")]
+	Cancelled,
+	/// The amount is negative or NaN, or a step of `shrunk_concentric` too
+	/// small to shrink anything.
+	#[display(fmt = "invalid amount {}", _0)]
+	InvalidAmount(f32),
+	/// Only loops of inward arcs, as made by `from_gen_input`, and full
+	/// circles can be offset.
+	#[display(fmt = "segment {} is {}", _0, _1)]
+	Unsupported(SegIdx, Bend),
+	#[display(fmt = "segment {}: {}", _0, _1)]
+	InvalidSegment(SegIdx, InvalidArc),
+	/// The circles of the segments meeting at the corner stopped intersecting,
+	/// which happens when an event was missed or rounding pushed them apart.
+	#[display(fmt = "corner {} lost at offset {}", idx, offset)]
+	CornerLost { idx: SegIdx, offset: f32 },
+}
+
 impl ArcPoly {
+	pub fn indices(&self) -> impl DoubleEndedIterator<Item = SegIdx> {
+		(0..self.segments.len()).map(SegIdx)
+	}
+
+	pub fn get(&self, idx: SegIdx) -> Option<&Segment> {
+		self.segments.get(idx.0)
+	}
+
+	/// End of the segment at `idx`, which is where the next one starts
+	/// unless it is the only one.
+	pub fn end_of(&self, idx: SegIdx) -> SegEnd {
+		SegEnd::in_loop(&self.segments, idx.0)
+	}
+
+	/// Counter-clockwise full circle, a single segment ending where it starts.
+	pub fn circle(center: Vec2, radius: f32) -> ArcPoly {
+		ArcPoly {
+			segments: vec![Segment {
+				initial: center + radius * Vec2::X,
+				center,
+				bend: Bend::Outward,
+				attributes: default(),
+			}],
+		}
+	}
+
+	/// Closed polygon of straight segments through `points`, the first of
+	/// which is not repeated at the end.
+	pub fn polygon(points: &[Vec2]) -> ArcPoly {
+		let n = points.len();
+		let segments = (0..n)
+			.map(|i| Segment::straight(points[i], points[(i + 1) % n]))
+			.collect();
+		ArcPoly { segments }
+	}
+
+	/// Counter-clockwise loop of straight segments around `rect`, starting at
+	/// its minimum corner.
+	pub fn rectangle(rect: Rect) -> ArcPoly {
+		ArcPoly::polygon(&[
+			rect.min,
+			Vec2::new(rect.max.x, rect.min.y),
+			rect.max,
+			Vec2::new(rect.min.x, rect.max.y),
+		])
+	}
+
+	/// Tangent discontinuities whose absolute turning angle is at least
+	/// `angle_threshold`. Assumes the counter-clockwise orientation produced
+	/// by `from_gen_input`, so left turns are convex. The turning angle is
+	/// derived from the chords and half sweeps, so sharp cusps between inward
+	/// bends may exceed π.
+	pub fn corners(&self, angle_threshold: f32) -> Vec<CornerInfo> {
+		let n = self.segments.len();
+		let mut corners: Vec<CornerInfo> = vec![];
+		if n < 2 {
+			return corners;
+		}
+		for j in self.indices() {
+			let (a, b, c) = (&self[j.prev(n)], &self[j], &self[j.next(n)]);
+			let chord_a = b.initial - a.initial;
+			let chord_b = c.initial - b.initial;
+			let turning_angle = chord_a.perp_dot(chord_b).atan2(chord_a.dot(chord_b))
+				- 0.5 * a.sweep(SegEnd::At(b.initial))
+				- 0.5 * b.sweep(SegEnd::At(c.initial));
+			if turning_angle.abs() >= angle_threshold {
+				corners.push(CornerInfo {
+					idx: j,
+					place: b.initial,
+					turning_angle,
+					convexity: if turning_angle > 0.0 {
+						Convexity::Convex
+					} else {
+						Convexity::Concave
+					},
+				});
+			}
+		}
+		corners
+	}
+
+	/// Number of counter-clockwise turns the boundary makes around `point`.
+	/// Angles are summed in `f64` and lens crossings counted as whole turns,
+	/// so that rounding does not add up to a wrong count on long loops.
+	pub fn winding_number(&self, point: &Vec2) -> i32 {
+		let mut angle = 0.0;
+		let mut turns = 0;
+		let origin = point.as_dvec2();
+		let n = self.segments.len();
+		for i in self.indices() {
+			let (a, b) = (&self[i], &self[i.next(n)]);
+			let (pa, pb) =
+				(a.initial.as_dvec2() - origin, b.initial.as_dvec2() - origin);
+			// A point on a chord is right of it, as in `Segment::in_lens`.
+			let cross = match pa.perp_dot(pb) {
+				0.0 => -0.0,
+				cross => cross,
+			};
+			angle += cross.atan2(pa.dot(pb));
+			let end = self.end_of(i);
+			if a.in_lens(end, point) {
+				turns += a.sweep(end).signum() as i32;
+			}
+		}
+		(angle / TAU).round() as i32 + turns
+	}
+
+	pub fn contains_point(&self, point: &Vec2) -> bool {
+		self.winding_number(point) != 0
+	}
+
+	/// Tight axis aligned bounds of each segment, see `Segment::bounds`.
+	pub fn segment_bounds(&self) -> impl Iterator<Item = Rect> + '_ {
+		self.indices().map(|i| self[i].bounds(self.end_of(i)))
+	}
+
+	/// Tight axis aligned bounds of the boundary, empty at the origin when
+	/// there are no segments.
+	pub fn bounds(&self) -> Rect {
+		self.segment_bounds().reduce(|a, b| a.union(b)).unwrap_or_default()
+	}
+
+	/// Side of the boundary on which `point` lies as seen travelling along it,
+	/// so left is inside counter-clockwise loops and outside clockwise ones,
+	/// i.e. holes. Points within `tolerance.boundary_distance` of the
+	/// boundary are `Side::On`. Being decided by the winding number rather
+	/// than the closest segment, this holds near corners as well.
+	pub fn side_of(&self, point: &Vec2, tolerance: &Tolerance) -> Side {
+		let on_boundary = self.closest_point(point).is_some_and(|(_, closest)| {
+			(closest - *point).length() <= tolerance.boundary_distance
+		});
+		if on_boundary {
+			Side::On
+		} else if self.contains_point(point)
+			== (self.orientation() == Orientation::CounterClockwise)
+		{
+			Side::Left
+		} else {
+			Side::Right
+		}
+	}
+
+	/// Containment of each of `points`. The segment bounds, grown by
+	/// `tolerance.boundary_distance`, are indexed once, so that each point is
+	/// checked for distance only against the segments whose bounds contain
+	/// it, and for winding only against those the ray from it along the x
+	/// axis passes, see `Segment::ray_crossings`.
+	pub fn classify_points(
+		&self,
+		points: &[Vec2],
+		tolerance: &Tolerance,
+	) -> Vec<Containment> {
+		let bvh = ArcBvh::new(
+			self
+				.segment_bounds()
+				.map(|r| r.inset(tolerance.boundary_distance))
+				.collect(),
+		);
+		points
+			.iter()
+			.map(|p| {
+				let on_boundary = bvh.query_point(*p).into_iter().any(|i| {
+					let closest =
+						self[SegIdx(i)].closest_point(self.end_of(SegIdx(i)), p);
+					(closest - *p).length() <= tolerance.boundary_distance
+				});
+				let winding = || {
+					bvh
+						.query_ray(*p, Vec2::X)
+						.into_iter()
+						.map(|i| self[SegIdx(i)].ray_crossings(self.end_of(SegIdx(i)), p))
+						.sum::<i32>()
+				};
+				if on_boundary {
+					Containment::OnBoundary
+				} else if winding() != 0 {
+					Containment::Inside
+				} else {
+					Containment::Outside
+				}
+			})
+			.collect()
+	}
+
+	/// Samples `kind` on a grid covering `bounds`, optionally spread over the
+	/// compute task pool, which must have been initialized. The grid is empty
+	/// for a `step` which is not positive and finite, see `Grid::from_fn`.
+	pub fn sample_grid(
+		&self,
+		bounds: Rect,
+		step: f32,
+		kind: SampleKind,
+		tolerance: &Tolerance,
+		parallel: bool,
+	) -> Grid<f32> {
+		Grid::from_fn(bounds, step, |points| {
+			if parallel {
+				let chunk_size = points.len() / ComputeTaskPool::get().thread_num() + 1;
+				points
+					.par_chunk_map(ComputeTaskPool::get(), chunk_size, |chunk| {
+						self.sample(chunk, kind, tolerance)
+					})
+					.concat()
+			} else {
+				self.sample(points, kind, tolerance)
+			}
+		})
+	}
+
+	fn sample(
+		&self,
+		points: &[Vec2],
+		kind: SampleKind,
+		tolerance: &Tolerance,
+	) -> Vec<f32> {
+		match kind {
+			SampleKind::Containment => self
+				.classify_points(points, tolerance)
+				.iter()
+				.map(|c| match c {
+					Containment::Inside => 1.0,
+					Containment::Outside => 0.0,
+					Containment::OnBoundary => 0.5,
+				})
+				.collect(),
+			SampleKind::Distance => points
+				.iter()
+				.map(|p| {
+					let distance = self
+						.closest_point(p)
+						.map_or(f32::INFINITY, |(_, q)| (q - *p).length());
+					if self.contains_point(p) {
+						-distance
+					} else {
+						distance
+					}
+				})
+				.collect(),
+		}
+	}
+
+	/// Circle enclosing the boundary including the bulges of its arcs, or
+	/// `None` when empty. It is the minimal enclosing circle of samples within
+	/// `tolerance` of the boundary, grown to reach the farthest point of every
+	/// segment, so it exceeds the minimal one by at most `tolerance`.
+	pub fn bounding_circle(&self, tolerance: f32) -> Option<Circle> {
+		let samples = self
+			.indices()
+			.flat_map(|i| self[i].sample(self.end_of(i), tolerance).collect_vec())
+			.collect_vec();
+		let circle = min_enclosing_circle(&samples)?;
+		let radius = self
+			.indices()
+			.map(|i| {
+				let far = self[i].farthest_point(self.end_of(i), &circle.v);
+				(far - circle.v).length()
+			})
+			.fold(circle.f, f32::max);
+		Some(FloatVec2 { f: radius, v: circle.v })
+	}
+
+	/// Index of the segment closest to `point` and the closest point on it.
+	pub fn closest_point(&self, point: &Vec2) -> Option<(SegIdx, Vec2)> {
+		self
+			.indices()
+			.map(|i| (i, self[i].closest_point(self.end_of(i), point)))
+			.min_by(|(_, p), (_, q)| {
+				(*p - *point).length().total_cmp(&(*q - *point).length())
+			})
+	}
+
+	/// Boundary of the union with the region of `other`, see
+	/// `boolean::union`.
+	pub fn union(&self, other: &ArcPoly, tolerance: &Tolerance) -> Vec<ArcPoly> {
+		boolean::union(
+			std::slice::from_ref(self),
+			std::slice::from_ref(other),
+			tolerance,
+		)
+	}
+
+	/// The part of the region within the rectangle from `min` to `max`, with
+	/// arcs split where they leave it. Regions entirely inside or outside the
+	/// rectangle are returned as is or dropped without overlaying them.
+	pub fn clip_rect(
+		&self,
+		min: Vec2,
+		max: Vec2,
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		let rect = Rect::from_corners(min, max);
+		let bounds = self.bounds();
+		if rect.intersect(bounds).is_empty() {
+			vec![]
+		} else if rect.contains(bounds.min) && rect.contains(bounds.max) {
+			vec![self.clone()]
+		} else {
+			self.intersect_region(&ArcPoly::rectangle(rect), tolerance)
+		}
+	}
+
+	/// See `boolean::intersection`.
+	pub fn intersect_region(
+		&self,
+		other: &ArcPoly,
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		boolean::intersection(
+			std::slice::from_ref(self),
+			std::slice::from_ref(other),
+			tolerance,
+		)
+	}
+
+	/// See `boolean::difference`.
+	pub fn difference(
+		&self,
+		other: &ArcPoly,
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		boolean::difference(
+			std::slice::from_ref(self),
+			std::slice::from_ref(other),
+			tolerance,
+		)
+	}
+
+	/// See `boolean::symmetric_difference`.
+	pub fn symmetric_difference(
+		&self,
+		other: &ArcPoly,
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		boolean::symmetric_difference(
+			std::slice::from_ref(self),
+			std::slice::from_ref(other),
+			tolerance,
+		)
+	}
+
+	/// `union` with the polygon through `points`, whose edges are kept
+	/// straight rather than approximated by arcs.
+	pub fn union_with_polygon(
+		&self,
+		points: &[Vec2],
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		self.union(&ArcPoly::polygon(points), tolerance)
+	}
+
+	/// `intersect_region` with the polygon through `points`.
+	pub fn intersect_polygon(
+		&self,
+		points: &[Vec2],
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		self.intersect_region(&ArcPoly::polygon(points), tolerance)
+	}
+
+	/// `difference` with the polygon through `points`.
+	pub fn difference_with_polygon(
+		&self,
+		points: &[Vec2],
+		tolerance: &Tolerance,
+	) -> Vec<ArcPoly> {
+		self.difference(&ArcPoly::polygon(points), tolerance)
+	}
+
+	/// See `boolean::clip_path`.
+	pub fn clip_path(
+		&self,
+		path: &[Vec2],
+		inside: bool,
+		tolerance: &Tolerance,
+	) -> Vec<Vec<Vec2>> {
+		boolean::clip_path(std::slice::from_ref(self), path, inside, tolerance)
+	}
+
+	/// Points where the boundary meets `circle`, in order along the boundary,
+	/// each with the index of the segment it lies on. Curves within `tol` of
+	/// touching are considered tangent.
+	pub fn intersect_circle(
+		&self,
+		circle: &Circle,
+		tol: f32,
+	) -> Vec<(SegIdx, Contact)> {
+		self
+			.indices()
+			.flat_map(|i| {
+				self[i]
+					.intersect_circle(self.end_of(i), circle, tol)
+					.into_iter()
+					.map(move |contact| (i, contact))
+			})
+			.collect()
+	}
+
+	/// Reindexes the loop to start at `start`, splitting the segment it lies
+	/// on unless it is within `tolerance.merge_distance` of an existing start.
+	pub fn rotate_start_to(
+		&mut self,
+		start: StartPosition,
+		tolerance: &Tolerance,
+	) {
+		let n = self.segments.len();
+		if n == 0 {
+			return;
+		}
+		let end = |i: usize| self.end_of(SegIdx(i));
+		let (i, place) = match start {
+			StartPosition::Point(point) => {
+				let (SegIdx(i), place) = self.closest_point(&point).unwrap();
+				(i, place)
+			}
+			StartPosition::Length(length) => {
+				let lengths =
+					(0..n).map(|i| self.segments[i].length(end(i))).collect_vec();
+				let mut rest = length.rem_euclid(lengths.iter().sum());
+				let mut i = 0;
+				while i + 1 < n && rest > lengths[i] {
+					rest -= lengths[i];
+					i += 1;
+				}
+				let t = if lengths[i] > 0.0 { rest / lengths[i] } else { 0.0 };
+				(i, self.segments[i].point_at(end(i), t))
+			}
+		};
+		let first = if (place - self.segments[i].initial).length()
+			< tolerance.merge_distance
+		{
+			i
+		} else if (place - self.segments[i].end_point(end(i))).length()
+			< tolerance.merge_distance
+		{
+			SegIdx(i).next(n).0
+		} else {
+			let split = Segment { initial: place, ..self.segments[i] };
+			self.segments.insert(i + 1, split);
+			i + 1
+		};
+		self.segments.rotate_left(first);
+	}
+
 	pub fn draw(&self, gizmos: &mut Gizmos, color: &Color) {
-		for (i, j) in (0..self.segments.len()).circular_tuple_windows() {
-			let (a, b) = (&self.segments[i], &self.segments[j]);
-			draw_segment(a, &b.initial, gizmos, color);
+		for i in self.indices() {
+			draw_segment(&self[i], self.end_of(i), gizmos, color);
+		}
+	}
+
+	/// Inward offset by `amount`, i.e. the erosion by a disc of that radius,
+	/// in which parts narrower than twice `amount` collapse, splitting the
+	/// polygon or making pieces vanish. Fails for negative amounts, which
+	/// would be an outward offset that the search for collisions, looking
+	/// only ahead in time, cannot check, and for loops other than those
+	/// `check_shrinkable` accepts. Thresholds are taken from `tolerance`, see
+	/// `shrunk_with`.
+	pub fn shrunk(
+		&self,
+		amount: f32,
+		tolerance: &Tolerance,
+	) -> std::result::Result<Vec<ArcPoly>, ShrinkError> {
+		self.shrunk_with(
+			amount,
+			&FloatBackend::default(),
+			tolerance,
+			&Cancellation::default(),
+			&mut |_| {},
+		)
+	}
+
+	/// `shrunk` which gives up once `cancellation` is cancelled, and reports
+	/// the fraction of `amount` processed so far to `progress` after each
+	/// collision event. An `amount` of at most `tolerance.merge_distance`,
+	/// zero included, yields the polygon itself, which is that close to the
+	/// offset, rather than its naive shrink, which would recompute every
+	/// corner from the neighboring circles and may lose corners between
+	/// nearly tangent arcs to rounding. All intersections are computed by
+	/// `backend`, and collisions are accepted by `tolerance`, see
+	/// `neighbor_collisions`.
+	pub fn shrunk_with(
+		&self,
+		amount: f32,
+		backend: &dyn IntersectionBackend,
+		tolerance: &Tolerance,
+		cancellation: &Cancellation,
+		progress: &mut dyn FnMut(f32),
+	) -> std::result::Result<Vec<ArcPoly>, ShrinkError> {
+		if amount.is_nan() || amount < 0.0 {
+			return Err(ShrinkError::InvalidAmount(amount));
+		}
+		if amount <= tolerance.merge_distance {
+			progress(1.0);
+			return Ok(vec![self.clone()]);
+		}
+		self.check_shrinkable(tolerance)?;
+		let mut reached = 0.0;
+		let mut report = |offset: f32| {
+			reached = f32::max(reached, offset);
+			progress(if amount > 0.0 { reached / amount } else { 1.0 });
+		};
+		let res = self.shrunk_from(
+			0.0,
+			amount,
+			backend,
+			tolerance,
+			cancellation,
+			&mut report,
+		);
+		if res.is_ok() {
+			report(amount);
+		}
+		res
+	}
+
+	/// Whether the loop is one `shrunk_with` can offset: a full circle, or a
+	/// loop of inward arcs only, failing with the first other segment. The
+	/// arcs of the latter also need a radius of at least
+	/// `tolerance.min_radius`, as the corners at either end of a point-like
+	/// arc cannot be told apart, whereas a full circle of zero radius simply
+	/// vanishes.
+	pub fn check_shrinkable(
+		&self,
+		tolerance: &Tolerance,
+	) -> std::result::Result<(), ShrinkError> {
+		let n = self.segments.len();
+		for i in self.indices() {
+			let segment = &self[i];
+			let supported = match n {
+				1 => segment.bend != Bend::Straight,
+				_ => segment.bend == Bend::Inward,
+			};
+			if !supported {
+				return Err(ShrinkError::Unsupported(i, segment.bend));
+			}
+			let radius = segment.radius();
+			if n > 1 && (radius.is_nan() || radius < tolerance.min_radius) {
+				return Err(ShrinkError::InvalidSegment(
+					i,
+					InvalidArc::RadiusTooSmall(radius),
+				));
+			}
 		}
+		Ok(())
 	}
 
-	pub fn shrunk(&self, gizmos: &mut Gizmos, amount: f32) -> Vec<ArcPoly> {
-		let collisions = self.future_collisions();
+	fn shrunk_from(
+		&self,
+		offset: f32,
+		amount: f32,
+		backend: &dyn IntersectionBackend,
+		tolerance: &Tolerance,
+		cancellation: &Cancellation,
+		report: &mut dyn FnMut(f32),
+	) -> std::result::Result<Vec<ArcPoly>, ShrinkError> {
+		if cancellation.is_cancelled() {
+			return Err(ShrinkError::Cancelled);
+		}
+		if amount <= tolerance.merge_distance {
+			return Ok(vec![self.clone()]);
+		}
+		if let [segment] = self.segments.as_slice() {
+			// A full circle shrinks concentrically without collisions.
+			let radius =
+				segment.radius() - amount * bool_to_sign(segment.bend == Bend::Outward);
+			if radius <= 0.0 {
+				return Ok(vec![]);
+			}
+			let initial = segment.center + radius * segment.ca().normalize();
+			return Ok(vec![ArcPoly {
+				segments: vec![Segment { initial, ..*segment }],
+			}]);
+		}
+		let collisions = self.future_collisions(backend, tolerance)?;
 		if let Some(c) = collisions.first() {
 			let t = c.time_place.f;
 			if 0.0 < t && t < amount {
-				let shrunk = self.shrink_naive(t + f32::EPSILON);
+				report(offset + t);
+				// Just past the event the segments taking part have been cut
+				// off or split, which at the event itself rounding may hide.
+				let after = t + tolerance.event_margin;
+				let shrunk = self.shrink_naive(after, backend)?;
 				let n = self.segments.len();
 				if n <= 3 {
-					return vec![];
+					return Ok(vec![]);
+				}
+				// Events up to `merge_distance` later happen at the same
+				// offset, and are applied together so that no child starts
+				// out with a segment about to vanish or a pinch about to split.
+				let simultaneous = collisions
+					.iter()
+					.take_while(|other| {
+						other.time_place.f - t <= tolerance.merge_distance
+					})
+					.collect_vec();
+				let children = apply_collisions(shrunk, &simultaneous);
+				let mut res: Vec<ArcPoly> = vec![];
+				for child in children {
+					res.append(&mut child.shrunk_from(
+						offset + after,
+						(amount - after).max(0.0),
+						backend,
+						tolerance,
+						cancellation,
+						report,
+					)?);
+				}
+				return Ok(res);
+			}
+		}
+		Ok(vec![self.shrink_naive(amount, backend)?])
+	}
+
+	/// `Segment::validate` of every segment, failing with the index of the
+	/// first invalid one.
+	pub fn validate(
+		&self,
+		tolerance: &Tolerance,
+	) -> std::result::Result<(), (SegIdx, InvalidArc)> {
+		self.indices().try_for_each(|i| {
+			self[i].validate(self.end_of(i), tolerance).map_err(|err| (i, err))
+		})
+	}
+
+	/// Everything wrong with the loop rather than the first invalid segment
+	/// as by `validate`: invalid segments, arcs not ending on their circle
+	/// and crossings of the boundary with itself. Touching segments are not
+	/// reported, as booleans and offsets legitimately produce them.
+	pub fn diagnose(&self, tolerance: &Tolerance) -> Vec<ValidationIssue> {
+		let n = self.segments.len();
+		if n == 0 {
+			return vec![ValidationIssue::Empty];
+		}
+		let mut issues = vec![];
+		for i in self.indices() {
+			let (segment, next) = (&self[i], self[i.next(n)].initial);
+			if let Err(err) = segment.validate(self.end_of(i), tolerance) {
+				issues.push(ValidationIssue::InvalidSegment(i, err));
+				continue;
+			}
+			let drift = ((next - segment.center).length() - segment.radius()).abs();
+			if segment.bend != Bend::Straight && drift > tolerance.merge_distance {
+				issues.push(ValidationIssue::EndOffCircle { idx: i, drift });
+			}
+		}
+		let bounds = self
+			.segment_bounds()
+			.map(|r| r.inset(tolerance.merge_distance))
+			.collect();
+		for (i, j) in ArcBvh::new(bounds).overlapping_pairs() {
+			let (first, second) = (SegIdx(i), SegIdx(j));
+			let joints = [self[first].initial, self[second].initial];
+			for contact in self[first].contacts_with_segment(
+				self.end_of(first),
+				&self[second],
+				self.end_of(second),
+				tolerance.merge_distance,
+			) {
+				let at_joint = (first.next(n) == second || second.next(n) == first)
+					&& joints.iter().any(|joint| {
+						(contact.point - *joint).length() < tolerance.merge_distance
+					});
+				if !contact.tangent && !at_joint {
+					issues.push(ValidationIssue::Crossing {
+						first,
+						second,
+						point: contact.point,
+					});
 				}
-				let children = match c.kind {
-					CollisionType::Opposite { first_idx: first, second_idx: second } => {
-						split_opposite(shrunk, c.time_place.v, first, second)
+			}
+		}
+		issues
+	}
+
+	/// Restores the invariant that every arc ends on its own circle, which
+	/// editing a start point alone breaks. Segments shorter than `tolerance`
+	/// are removed, and arcs whose end is more than `tolerance` off their
+	/// circle get their center moved to the nearest point equidistant from
+	/// both ends. Returns the number of segments removed or moved.
+	pub fn repair_joints(&mut self, tolerance: f32) -> usize {
+		let mut repaired = 0;
+		let mut i = 0;
+		while self.segments.len() > 1 && i < self.segments.len() {
+			let next = self[SegIdx(i).next(self.segments.len())].initial;
+			if (next - self.segments[i].initial).length() < tolerance {
+				self.segments.remove(i);
+				repaired += 1;
+			} else {
+				i += 1;
+			}
+		}
+		let n = self.segments.len();
+		for i in self.indices() {
+			let next = self[i.next(n)].initial;
+			let segment = &mut self.segments[i.0];
+			let drift = (next - segment.center).length() - segment.radius();
+			if segment.bend == Bend::Straight || drift.abs() <= tolerance {
+				continue;
+			}
+			recenter(segment, &next);
+			repaired += 1;
+		}
+		repaired
+	}
+
+	/// `loops` without arcs shorter than `min_arc_length`, whose neighbours
+	/// are extended to close the gap, and without loops then enclosing less
+	/// than `min_loop_area` in absolute value, such as the slivers left by
+	/// near-tangent intersections.
+	pub fn prune_small(
+		loops: Vec<ArcPoly>,
+		min_arc_length: f32,
+		min_loop_area: f32,
+	) -> Vec<ArcPoly> {
+		loops
+			.into_iter()
+			.filter_map(|mut poly| {
+				let mut i = 0;
+				while i < poly.segments.len() {
+					let n = poly.segments.len();
+					let end = SegEnd::in_loop(&poly.segments, i);
+					if poly.segments[i].length(end) >= min_arc_length {
+						i += 1;
+						continue;
+					}
+					poly.segments.remove(i);
+					if n > 2 {
+						let previous = (i + n - 2) % (n - 1);
+						let next = poly.segments[i % (n - 1)].initial;
+						recenter(&mut poly.segments[previous], &next);
 					}
-					CollisionType::Neighbors { idx: i } => {
-						vec![shrunk.with_removed(i)]
+				}
+				(poly.area().abs() >= min_loop_area && !poly.segments.is_empty())
+					.then_some(poly)
+			})
+			.collect()
+	}
+
+	/// The loop with runs of consecutive segments replaced by single arcs
+	/// deviating at most `tolerance` from them, e.g. to cut down the segment
+	/// count after `prune_small`. Runs are grown greedily from the start and
+	/// only across segments with equal attributes. The kept joints are the
+	/// original ones, so the loop stays closed exactly. A `tolerance` which
+	/// is not positive leaves the loop as it is.
+	pub fn simplified(&self, tolerance: f32) -> ArcPoly {
+		if tolerance.is_nan() || tolerance <= 0.0 {
+			return self.clone();
+		}
+		let n = self.segments.len();
+		let next = |i: usize| self[SegIdx(i).next(n)].initial;
+		let end = |i: usize| SegEnd::At(next(i));
+		let mut segments = vec![];
+		let mut i = 0;
+		while i < n {
+			let mut merged = self.segments[i];
+			let mut j = i + 1;
+			// A run must not cover the whole loop, whose end is its start.
+			while j < n && j - i + 1 < n {
+				let run = &self.segments[i..=j];
+				if run.iter().any(|s| s.attributes != merged.attributes) {
+					break;
+				}
+				let lengths = (i..=j).map(|k| self.segments[k].length(end(k)));
+				let half = 0.5 * lengths.clone().sum::<f32>();
+				let mut walked = 0.0;
+				let mut mid = next(j);
+				for (k, length) in (i..=j).zip(lengths) {
+					if walked + length >= half {
+						mid = self.segments[k].point_at_length(end(k), half - walked);
+						break;
 					}
+					walked += length;
+				}
+				let candidate = Segment {
+					attributes: merged.attributes,
+					..Segment::from_three_points(self.segments[i].initial, mid, next(j))
 				};
-				return children
-					.iter()
-					.flat_map(|x| x.shrunk(gizmos, amount - t))
-					.collect_vec();
+				let within = (i..=j).all(|k| {
+					self.segments[k]
+						.sample(end(k), 0.5 * tolerance)
+						.all(|p| candidate.distance_to_point(end(j), &p) <= tolerance)
+				});
+				if !within {
+					break;
+				}
+				merged = candidate;
+				j += 1;
 			}
+			segments.push(merged);
+			i = j;
 		}
-		vec![self.shrink_naive(amount)]
+		ArcPoly { segments }
 	}
 
-	pub fn future_collisions(&self) -> Vec<Collision> {
-		let mut collisions: Vec<Collision> = self.opposite_collisions();
-		collisions.append(&mut self.neighbor_collisions());
+	/// Offsets by `step`, `2 * step`, ... up to `count` steps or until nothing
+	/// is left. Each offset is computed from the previous one, which is the
+	/// same as shrinking the original by the accumulated amount. Fails for a
+	/// `step` within `tolerance.merge_distance`, by which `shrunk` leaves the
+	/// loop as it is, so that it would never run out.
+	pub fn shrunk_concentric(
+		&self,
+		step: f32,
+		count: usize,
+		tolerance: &Tolerance,
+	) -> std::result::Result<Vec<Vec<ArcPoly>>, ShrinkError> {
+		if step.is_nan() || step <= tolerance.merge_distance {
+			return Err(ShrinkError::InvalidAmount(step));
+		}
+		let mut res: Vec<Vec<ArcPoly>> = vec![];
+		let mut current = vec![self.clone()];
+		for _ in 0..count {
+			current = current
+				.iter()
+				.map(|poly| poly.shrunk(step, tolerance))
+				.flatten_ok()
+				.try_collect()?;
+			if current.is_empty() {
+				break;
+			}
+			res.push(current.clone());
+		}
+		Ok(res)
+	}
+
+	/// Runs `shrunk_with` on the async compute task pool, which must have been
+	/// initialized, e.g. by bevy's `TaskPoolPlugin`. Dropping the task only
+	/// prevents it from starting, use `cancellation` to abort it.
+	pub fn shrunk_async(
+		&self,
+		amount: f32,
+		tolerance: Tolerance,
+		cancellation: Cancellation,
+	) -> Task<std::result::Result<Vec<ArcPoly>, ShrinkError>> {
+		let poly = self.clone();
+		AsyncComputeTaskPool::get().spawn(async move {
+			poly.shrunk_with(
+				amount,
+				&FloatBackend::default(),
+				&tolerance,
+				&cancellation,
+				&mut |_| {},
+			)
+		})
+	}
+
+	pub fn future_collisions(
+		&self,
+		backend: &dyn IntersectionBackend,
+		tolerance: &Tolerance,
+	) -> std::result::Result<Vec<Collision>, ShrinkError> {
+		let mut collisions: Vec<Collision> =
+			self.opposite_collisions(backend, tolerance)?;
+		collisions.append(&mut self.neighbor_collisions(backend, tolerance)?);
 		collisions.sort_by(|c1, c2| c1.time_place.f.total_cmp(&c2.time_place.f));
-		collisions
+		Ok(collisions)
 	}
 
-	pub fn neighbor_collisions(&self) -> Vec<Collision> {
+	/// Events in which a segment vanishes between its neighbours, where the
+	/// circles of the three meet. Only meetings which both ends of the
+	/// segment, as shrunk to `tolerance.event_margin` before the event, reach
+	/// within `tolerance.collision_distance` are kept.
+	pub fn neighbor_collisions(
+		&self,
+		backend: &dyn IntersectionBackend,
+		tolerance: &Tolerance,
+	) -> std::result::Result<Vec<Collision>, ShrinkError> {
 		let mut vec: Vec<Collision> = vec![];
 		let n = self.segments.len();
-		for i in 0..n {
-			let h = (n - 1 + i) % n;
-			let j = (n + 1 + i) % n;
-			let prev = &self.segments[h];
-			let this = &self.segments[i];
-			let next = &self.segments[j];
-			let cols = three_circle_collision(
+		for i in self.indices() {
+			let j = i.next(n);
+			let prev = &self[i.prev(n)];
+			let this = &self[i];
+			let next = &self[j];
+			let cols = backend.three_circles(
 				&prev.circle_neg_r(),
 				&this.circle_neg_r(),
 				&next.circle_neg_r(),
@@ -99,29 +1060,38 @@ impl ArcPoly {
 			for col in cols {
 				let FloatVec2 { f: t, v: p } = col;
 				if t > 0.0 {
-					let shrunk = self.shrink_naive(t - f32::EPSILON);
-					let sthis = &shrunk.segments[i];
-					let snext = &shrunk.segments[j];
+					let before = (t - tolerance.event_margin).max(0.0);
+					let shrunk = self.shrink_naive(before, backend)?;
+					let sthis = &shrunk[i];
+					let snext = &shrunk[j];
 					let thisd = (sthis.initial - p).length();
 					let nextd = (snext.initial - p).length();
-					const LIMIT: f32 = 1.0;
-					if thisd < LIMIT && nextd < LIMIT {
+					let limit = tolerance.collision_distance;
+					if thisd < limit && nextd < limit {
 						vec.push(Collision {
 							time_place: col,
 							kind: CollisionType::Neighbors { idx: i },
+							involved: [i.prev(n), i, j].map(|k| shrunk.snapshot(k)).to_vec(),
 						});
 					}
 				}
 			}
 		}
-		vec
+		Ok(vec)
 	}
 
-	pub fn opposite_collisions(&self) -> Vec<Collision> {
+	/// Events in which two non-adjacent segments meet, splitting the loop,
+	/// told apart from meetings of their circles off the arcs by the naive
+	/// shrink `tolerance.event_margin` past the event.
+	pub fn opposite_collisions(
+		&self,
+		backend: &dyn IntersectionBackend,
+		tolerance: &Tolerance,
+	) -> std::result::Result<Vec<Collision>, ShrinkError> {
 		let mut vec: Vec<Collision> = vec![];
 		let n = self.segments.len();
 		if n <= 3 {
-			return vec![];
+			return Ok(vec![]);
 		}
 		for i in 0..n {
 			let first = &self.segments[i];
@@ -138,11 +1108,13 @@ impl ArcPoly {
 					let t = 0.5 * (dist - first_r - second_r);
 					if t >= 0.0 {
 						let place = first.center + (first_r + t) * center_line.normalize();
-						let naive = self.shrink_naive(t + f32::EPSILON);
-						let first_naive = naive.segments[i];
-						let second_naive = naive.segments[j];
-						let first_naive_next = naive.segments[(n + 1 + i) % n];
-						let second_naive_next = naive.segments[(n + 1 + j) % n];
+						let naive =
+							self.shrink_naive(t + tolerance.event_margin, backend)?;
+						let (i, j) = (SegIdx(i), SegIdx(j));
+						let first_naive = naive[i];
+						let second_naive = naive[j];
+						let first_naive_next = naive[i.next(n)];
+						let second_naive_next = naive[j.next(n)];
 						let [fbv, fba, sbv, sba] = [
 							angle_counter_clockwise(
 								&(first_naive_next.initial - first_naive.center),
@@ -165,17 +1137,74 @@ impl ArcPoly {
 							let col = Collision {
 								time_place: FloatVec2 { f: t, v: place },
 								kind: CollisionType::Opposite { first_idx: i, second_idx: j },
+								involved: vec![naive.snapshot(i), naive.snapshot(j)],
 							};
 
 							vec.push(col);
 						}
 					}
 				} else {
-					todo!()
+					let (i, j) = (SegIdx(i), SegIdx(j));
+					let idx = if first.bend != Bend::Inward { i } else { j };
+					return Err(ShrinkError::Unsupported(idx, self[idx].bend));
 				}
 			}
 		}
-		vec
+		Ok(vec)
+	}
+
+	/// Area enclosed by the boundary, positive when counter-clockwise: that
+	/// of the polygon of chords plus the circular segments between each chord
+	/// and its arc.
+	pub fn area(&self) -> f32 {
+		let n = self.segments.len();
+		self
+			.indices()
+			.map(|i| {
+				let (segment, next) = (&self[i], &self[i.next(n)]);
+				0.5 * segment.initial.perp_dot(next.initial)
+					+ segment.lens_area(self.end_of(i))
+			})
+			.sum()
+	}
+
+	pub fn perimeter(&self) -> f32 {
+		self.indices().map(|i| self[i].length(self.end_of(i))).sum()
+	}
+
+	/// Points along the boundary at most `spacing` apart, each segment split
+	/// into equal steps from its start, paired with the unit normal to the
+	/// right of the direction of travel as taken from the arc geometry rather
+	/// than from a flattening. This points away from the region of
+	/// counter-clockwise outer loops and clockwise holes alike. A `spacing`
+	/// which is not positive yields no points.
+	pub fn sample_boundary(&self, spacing: f32) -> Vec<(Vec2, Vec2)> {
+		if spacing.is_nan() || spacing <= 0.0 {
+			return vec![];
+		}
+		let mut samples = vec![];
+		for i in self.indices() {
+			let (segment, end) = (&self[i], self.end_of(i));
+			let steps = (segment.length(end) / spacing).ceil().max(1.0);
+			for point in segment.sample_n(end, steps as usize).dropping_back(1) {
+				samples.push((point, segment.normal_at(end, &point)));
+			}
+		}
+		samples
+	}
+
+	/// Average of the points along the boundary, weighted by arc length, or
+	/// `None` when the boundary has no length.
+	pub fn boundary_centroid(&self) -> Option<Vec2> {
+		let (moment, length) = self
+			.indices()
+			.map(|i| {
+				let (segment, end) = (&self[i], self.end_of(i));
+				let length = segment.length(end);
+				(length * segment.centroid(end), length)
+			})
+			.fold((Vec2::ZERO, 0.0), |(m, l), (dm, dl)| (m + dm, l + dl));
+		(length > 0.0).then(|| moment / length)
 	}
 
 	pub fn max_arc_length(&self) -> f32 {
@@ -187,34 +1216,84 @@ impl ArcPoly {
 			.unwrap_or(f32::MAX)
 	}
 
-	pub fn with_removed(&self, idx: usize) -> ArcPoly {
+	/// Orientation by the sign of `area`, counter-clockwise for degenerate
+	/// loops enclosing no area.
+	pub fn orientation(&self) -> Orientation {
+		if self.area() < 0.0 {
+			Orientation::Clockwise
+		} else {
+			Orientation::CounterClockwise
+		}
+	}
+
+	/// Reverses the loop unless it already has `orientation`, which flips the
+	/// bend of every arc along with the order of the segments.
+	pub fn normalize_orientation(&mut self, orientation: Orientation) {
+		if self.orientation() != orientation {
+			*self = self.reversed();
+		}
+	}
+
+	/// The same boundary travelled in the opposite direction, starting at the
+	/// end of the last segment.
+	pub fn reversed(&self) -> ArcPoly {
+		let segments =
+			self.indices().rev().map(|i| self[i].reversed(self.end_of(i))).collect();
+		ArcPoly { segments }
+	}
+
+	/// Copy of the segment at `idx` with its end, see `SegmentSnapshot`.
+	pub fn snapshot(&self, idx: SegIdx) -> SegmentSnapshot {
+		let segment = self[idx];
+		SegmentSnapshot {
+			idx,
+			segment,
+			end: self.end_of(idx),
+			radius: segment.radius(),
+		}
+	}
+
+	pub fn with_removed(&self, idx: SegIdx) -> ArcPoly {
 		let mut clone = self.clone();
-		clone.segments.remove(idx);
+		clone.segments.remove(idx.0);
 		clone
 	}
 
-	pub fn shrink_naive(&self, amount: f32) -> ArcPoly {
+	/// Every arc moved inward by `amount` with the corners recomputed from
+	/// the neighboring circles, without regard for collisions. Fails for
+	/// segments other than inward arcs.
+	pub fn shrink_naive(
+		&self,
+		amount: f32,
+		backend: &dyn IntersectionBackend,
+	) -> std::result::Result<ArcPoly, ShrinkError> {
 		let n = self.segments.len();
 		let mut segs: Vec<Segment> = vec![];
-		for j in 0..n {
-			let i = (n - 1 + j) % n;
-			let (a, b) = (&self.segments[i], &self.segments[j]);
+		for j in self.indices() {
+			let (a, b) = (&self[j.prev(n)], &self[j]);
 			if a.bend == Bend::Inward && b.bend == Bend::Inward {
 				let (mut ca, mut cb) = (a.circle(), b.circle());
 				ca.f += amount;
 				cb.f += amount;
-				let cols = two_circle_collision(&ca, &cb);
-				if cols.len() < 2 {
-					println!("{}, {}", ca, cb);
-					panic!("circles not intersecting")
-				}
-				segs.push(Segment { initial: cols[1], center: b.center, bend: b.bend });
+				// Tangent arcs stay tangent, meeting in a single point.
+				let corner = match backend.circle_circle(&ca, &cb) {
+					CircleIntersection::Crossing(_, corner)
+					| CircleIntersection::Tangent(corner) => corner,
+					_ => return Err(ShrinkError::CornerLost { idx: j, offset: amount }),
+				};
+				let initial = checked(
+					"ArcPoly::shrink_naive",
+					|| format!("{}, {}, {}", ca, cb, amount),
+					corner,
+				);
+				segs.push(Segment { initial, ..*b });
 			} else {
-				todo!();
+				let idx = if a.bend != Bend::Inward { j.prev(n) } else { j };
+				return Err(ShrinkError::Unsupported(idx, self[idx].bend));
 			}
 		}
 
-		ArcPoly { segments: segs }
+		Ok(ArcPoly { segments: segs })
 	}
 
 	pub fn from_gen_input(gen_input: &ArcPolyGenInput) -> Self {
@@ -227,7 +1306,7 @@ impl ArcPoly {
 				Vec2::new(
 					f32::cos(2.0 * PI * (i as f32) / (gen_input.n as f32)),
 					f32::sin(2.0 * PI * (i as f32) / (gen_input.n as f32)),
-				) * gen_input.r
+				) * gen_input.r.value()
 					+ Vec2::from_array(UnitDisc.sample(&mut rng))
 						* gen_input.offset_noise,
 			);
@@ -247,41 +1326,148 @@ impl ArcPoly {
 						* absolute_bend
 						* bool_to_sign(bend == Bend::Outward)),
 			);
-			res.segments.push(Segment { initial: a, center: c, bend: bend });
+			res.segments.push(Segment {
+				initial: a,
+				center: c,
+				bend,
+				attributes: default(),
+			});
 		}
 		res
 	}
 }
 
+impl ApproxEq for ArcPoly {
+	/// Loops are equal regardless of which of their segments comes first.
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		let n = self.segments.len();
+		n == other.segments.len()
+			&& (n == 0
+				|| (0..n).any(|shift| {
+					(0..n).all(|i| {
+						self.segments[i]
+							.approx_eq(&other.segments[(i + shift) % n], epsilon)
+					})
+				}))
+	}
+}
+
+impl Transform2 for ArcPoly {
+	fn apply_xform(&mut self, xform: &Xform2) {
+		for segment in self.segments.iter_mut() {
+			segment.apply_xform(xform);
+		}
+	}
+
+	/// Mirrors every segment and then reverses the loop, so that it keeps its
+	/// orientation and remains valid input to `shrunk`.
+	fn mirror(&mut self, origin: Vec2, dir: Vec2) {
+		for segment in self.segments.iter_mut() {
+			segment.mirror(origin, dir);
+		}
+		*self = self.reversed();
+	}
+}
+
+/// Moves the center of an arc to the nearest point equidistant from its
+/// start and `next_initial`, so that it ends there.
+fn recenter(segment: &mut Segment, next_initial: &Vec2) {
+	if segment.bend == Bend::Straight {
+		return;
+	}
+	let mid = midpoint(&segment.initial, next_initial);
+	let normal = (*next_initial - segment.initial).perp().normalize();
+	segment.center = mid + normal * (segment.center - mid).dot(normal);
+}
+
 pub fn split_opposite(
 	arc_poly: ArcPoly,
 	place: Vec2,
-	first_idx: usize,
-	second_idx: usize,
+	first_idx: SegIdx,
+	second_idx: SegIdx,
 ) -> Vec<ArcPoly> {
-	let n = arc_poly.segments.len();
+	split_tagged(tagged(arc_poly), place, first_idx, second_idx)
+		.into_iter()
+		.map(untagged)
+		.collect()
+}
+
+/// Segments of `arc_poly` together with their indices, which stay attached
+/// as the loop is split and segments are removed.
+fn tagged(arc_poly: ArcPoly) -> Vec<(SegIdx, Segment)> {
+	(0..arc_poly.segments.len()).map(SegIdx).zip(arc_poly.segments).collect()
+}
+
+fn untagged(segments: Vec<(SegIdx, Segment)>) -> ArcPoly {
+	ArcPoly { segments: segments.into_iter().map(|(_, s)| s).collect() }
+}
+
+/// `split_opposite` of a tagged loop, where both halves of a split segment
+/// keep its index.
+fn split_tagged(
+	segments: Vec<(SegIdx, Segment)>,
+	place: Vec2,
+	first_idx: SegIdx,
+	second_idx: SegIdx,
+) -> Vec<Vec<(SegIdx, Segment)>> {
 	let mut j: usize = 0;
-	let mut polys: Vec<ArcPoly> = vec![default(), default()];
-	for i in 0..n {
-		let segment = &arc_poly.segments[i];
-		if [first_idx, second_idx].contains(&i) {
-			let mut right = segment.clone();
-			right.initial = place;
-			polys[j].segments.push(segment.clone());
+	let mut parts = vec![vec![], vec![]];
+	for (idx, segment) in segments {
+		parts[j].push((idx, segment));
+		if [first_idx, second_idx].contains(&idx) {
 			j = (j + 1) % 2;
-			polys[j].segments.push(right);
-		} else {
-			polys[j].segments.push(segment.clone());
+			parts[j].push((idx, Segment { initial: place, ..segment }));
 		}
 	}
-	polys
+	parts
+}
+
+/// `arc_poly`, shrunk to the time of `collisions`, split at those between
+/// opposite segments and without the segments vanishing in those between
+/// neighbours. A split is skipped when an earlier one already separated its
+/// segments, as the two can then no longer meet.
+fn apply_collisions(
+	arc_poly: ArcPoly,
+	collisions: &[&Collision],
+) -> Vec<ArcPoly> {
+	let mut loops = vec![tagged(arc_poly)];
+	let mut vanishing = vec![];
+	for collision in collisions {
+		match collision.kind {
+			CollisionType::Opposite { first_idx, second_idx } => {
+				let holds = |idx: SegIdx| {
+					move |l: &Vec<(SegIdx, Segment)>| l.iter().any(|(i, _)| *i == idx)
+				};
+				let Some(k) = loops
+					.iter()
+					.position(|l| holds(first_idx)(l) && holds(second_idx)(l))
+				else {
+					continue;
+				};
+				let split = loops.swap_remove(k);
+				loops.extend(split_tagged(
+					split,
+					collision.time_place.v,
+					first_idx,
+					second_idx,
+				));
+			}
+			CollisionType::Neighbors { idx } => vanishing.push(idx),
+		}
+	}
+	loops
+		.into_iter()
+		.map(|l| l.into_iter().filter(|(i, _)| !vanishing.contains(i)).collect())
+		.map(untagged)
+		.filter(|poly| !poly.segments.is_empty())
+		.collect()
 }
 
 #[derive(Reflect, Resource)]
 pub struct ArcPolyGenInput {
 	pub random_seed: u32,
 	pub n: usize,
-	pub r: f32,
+	pub r: Param,
 	pub offset_noise: f32,
 	pub bend_max: f32,
 	pub bend_min: f32,
@@ -293,7 +1479,7 @@ impl Default for ArcPolyGenInput {
 		ArcPolyGenInput {
 			random_seed: 17,
 			n: 13,
-			r: 250.0,
+			r: Param::logarithmic(250.0, 0.1, 1000.0, "px"),
 			offset_noise: 50.0,
 			bend_max: 0.5,
 			bend_min: 0.02,
@@ -301,3 +1487,208 @@ impl Default for ArcPolyGenInput {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shrunk_rejects_negative_amounts() {
+		let poly = ArcPoly::from_gen_input(&ArcPolyGenInput::default());
+		for amount in [-1.0, f32::NAN] {
+			assert!(matches!(
+				poly.shrunk(amount, &Tolerance::default()),
+				Err(ShrinkError::InvalidAmount(_))
+			));
+		}
+		assert_eq!(poly.shrunk(0.0, &Tolerance::default()).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn shrunk_by_tiny_amounts_keeps_every_segment() {
+		let tolerance = Tolerance::default();
+		for (random_seed, n) in [(108, 20), (186, 13), (17, 13)] {
+			let input = ArcPolyGenInput { random_seed, n, ..default() };
+			let poly = ArcPoly::from_gen_input(&input);
+			for amount in [1e-7, 1e-5, 1e-2] {
+				let shrunk = poly.shrunk(amount, &Tolerance::default()).unwrap();
+				assert_eq!(shrunk.len(), 1);
+				assert_eq!(shrunk[0].segments.len(), poly.segments.len());
+				assert!(shrunk[0].validate(&tolerance).is_ok());
+			}
+		}
+	}
+
+	#[test]
+	fn shrunk_rejects_point_like_arcs() {
+		let mut poly = ArcPoly::from_gen_input(&ArcPolyGenInput::default());
+		poly.segments[2].center = poly.segments[2].initial;
+		assert!(matches!(
+			poly.shrunk(5.0, &Tolerance::default()),
+			Err(ShrinkError::InvalidSegment(
+				SegIdx(2),
+				InvalidArc::RadiusTooSmall(_)
+			))
+		));
+		assert!(ArcPoly::circle(Vec2::ZERO, 0.0)
+			.shrunk(5.0, &Tolerance::default())
+			.unwrap()
+			.is_empty());
+	}
+
+	#[test]
+	fn shrunk_at_other_scales() {
+		let summary = |polys: Vec<ArcPoly>, scale: f32| {
+			polys
+				.iter()
+				.map(|p| (p.segments.len(), p.area() / scale.powi(2)))
+				.collect_vec()
+		};
+		// Shrinks with a vanishing segment and splits into two loops.
+		for (random_seed, n, shrink) in
+			[(17, 13, 48.5), (24, 5, 48.5), (25, 5, 48.5)]
+		{
+			let input = ArcPolyGenInput { random_seed, n, shrink, ..default() };
+			let poly = ArcPoly::from_gen_input(&input);
+			let tolerance = Tolerance::default();
+			let expected =
+				summary(poly.shrunk(input.shrink, &tolerance).unwrap(), 1.0);
+			for scale in [1e-3, 1e3] {
+				let mut scaled = poly.clone();
+				scaled.apply_xform(&Xform2 { scale, ..Xform2::IDENTITY });
+				let shrunk = scaled
+					.shrunk(scale * input.shrink, &tolerance.scaled(scale))
+					.unwrap();
+				let found = summary(shrunk, scale);
+				assert_eq!(found.len(), expected.len(), "seed {}", random_seed);
+				for ((n, area), (expected_n, expected_area)) in
+					found.iter().zip(&expected)
+				{
+					assert_eq!(n, expected_n);
+					assert!((area - expected_area).abs() <= 1e-3 * expected_area.abs());
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn sampling_with_non_positive_tolerances_terminates() {
+		let poly = ArcPoly::from_gen_input(&ArcPolyGenInput::default());
+		for tolerance in [0.0, -1.0, f32::NAN] {
+			let segment = &poly.segments[0];
+			let points = segment.sample(poly.end_of(SegIdx(0)), tolerance);
+			assert!(points.count() < 100_000);
+			assert_eq!(
+				poly.simplified(tolerance).segments.len(),
+				poly.segments.len()
+			);
+			assert!(poly.sample_boundary(tolerance).is_empty());
+		}
+	}
+
+	#[test]
+	fn arcs_ending_where_they_start_have_no_length() {
+		let circle = ArcPoly::circle(Vec2::ZERO, 1.0);
+		assert_eq!(circle.perimeter(), 2.0 * PI);
+		let mut square = ArcPoly::rectangle(Rect::new(0.0, 0.0, 10.0, 10.0));
+		let corner = square.segments[1].initial;
+		square
+			.segments
+			.insert(1, Segment::from_center_start(corner + Vec2::X, corner, true));
+		assert_eq!(square.perimeter(), 40.0);
+		assert_eq!(square.area(), 100.0);
+		assert_eq!(square.boundary_centroid(), Some(Vec2::splat(5.0)));
+		assert!(square.validate(&Tolerance::default()).is_err());
+	}
+
+	#[test]
+	fn winding_number_on_a_loop_of_ten_thousand_arcs() {
+		let n = 10_000;
+		let radius = 1000.0;
+		let vertex = |k: usize| {
+			let angle = k as f32 / n as f32 * 2.0 * PI;
+			radius * Vec2::from_angle(angle)
+		};
+		// Scallops bulging alternately out of and into the circle.
+		let segments = (0..n)
+			.map(|k| {
+				let chord_mid = midpoint(&vertex(k), &vertex((k + 1) % n));
+				match k % 2 {
+					0 => Segment::from_center_start(0.5 * chord_mid, vertex(k), true),
+					_ => Segment::from_center_start(1.5 * chord_mid, vertex(k), false),
+				}
+			})
+			.collect_vec();
+		let poly = ArcPoly { segments };
+		let reversed = poly.reversed();
+		assert_eq!(poly.winding_number(&Vec2::ZERO), 1);
+		assert_eq!(reversed.winding_number(&Vec2::ZERO), -1);
+		for k in (0..n).step_by(97) {
+			let inside = 0.999 * vertex(k);
+			let outside = 1.001 * vertex(k);
+			assert_eq!(poly.winding_number(&inside), 1, "arc {}", k);
+			assert_eq!(poly.winding_number(&outside), 0, "arc {}", k);
+			assert_eq!(reversed.winding_number(&inside), -1, "arc {}", k);
+		}
+	}
+
+	#[test]
+	fn reversed_loops_wind_the_other_way() {
+		let poly = ArcPoly::from_gen_input(&ArcPolyGenInput::default());
+		let reversed = poly.reversed();
+		assert!((reversed.area() + poly.area()).abs() < 1e-6 * poly.area().abs());
+		assert!((reversed.perimeter() - poly.perimeter()).abs() < 1e-3);
+		assert!(reversed.orientation() == poly.orientation().opposite());
+		let center = poly.bounds().center();
+		assert_eq!(reversed.winding_number(&center), -poly.winding_number(&center));
+		assert!(reversed.reversed().approx_eq(&poly, 1e-4));
+	}
+
+	#[test]
+	fn approx_eq_ignores_the_first_segment() {
+		let poly = ArcPoly::from_gen_input(&ArcPolyGenInput::default());
+		let mut rotated = poly.clone();
+		rotated.segments.rotate_left(5);
+		assert!(rotated.approx_eq(&poly, 1e-6));
+		let mut moved = rotated.clone();
+		moved.segments[3].center += Vec2::splat(1e-2);
+		assert!(moved.approx_eq(&poly, 1e-1));
+		assert!(!moved.approx_eq(&poly, 1e-3));
+		let mut flipped = rotated;
+		flipped.segments[3].bend = flipped.segments[3].bend.flipped();
+		assert!(!flipped.approx_eq(&poly, 1e-1));
+		let mut square = ArcPoly::rectangle(Rect::new(0.0, 0.0, 1.0, 1.0));
+		let expected = square.clone();
+		square.segments[0].center = Vec2::splat(7.0);
+		assert!(square.approx_eq(&expected, 1e-6));
+	}
+
+	#[test]
+	fn classify_points_agrees_with_the_winding_number() {
+		let tolerance = Tolerance::default();
+		let mut polys = (0..8)
+			.map(|random_seed| {
+				ArcPoly::from_gen_input(&ArcPolyGenInput { random_seed, ..default() })
+			})
+			.collect_vec();
+		polys.push(ArcPoly::rectangle(Rect::new(-100.0, -50.0, 100.0, 50.0)));
+		polys.push(ArcPoly::circle(Vec2::ZERO, 100.0).reversed());
+		for poly in polys {
+			let bounds = poly.bounds().inset(10.0);
+			let points = Grid::from_fn(bounds, 7.0, |points| points.to_vec()).values;
+			let found = poly.classify_points(&points, &tolerance);
+			for (p, containment) in points.iter().zip(found) {
+				let expected = match poly.closest_point(p) {
+					Some((_, closest))
+						if (closest - *p).length() <= tolerance.boundary_distance =>
+					{
+						Containment::OnBoundary
+					}
+					_ if poly.contains_point(p) => Containment::Inside,
+					_ => Containment::Outside,
+				};
+				assert!(containment == expected, "{} at {}", poly, p);
+			}
+		}
+	}
+}
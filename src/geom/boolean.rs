@@ -0,0 +1,518 @@
+use bevy::{
+	math::{Rect, Vec2},
+	reflect::Reflect,
+};
+use derive_more::Display;
+use itertools::Itertools;
+
+use crate::math::{ApproxEq, Tolerance};
+
+use super::{
+	arc_poly::ArcPoly,
+	index::ArcBvh,
+	segment::{Bend, SegEnd, Segment},
+};
+
+/// Part of a boundary loop between consecutive intersection points.
+#[derive(Clone, Copy)]
+struct Piece {
+	segment: Segment,
+	end: SegEnd,
+}
+
+impl Piece {
+	fn end_point(&self) -> Vec2 {
+		self.segment.end_point(self.end)
+	}
+
+	fn midpoint(&self) -> Vec2 {
+		self.segment.point_at(self.end, 0.5)
+	}
+
+	fn reversed(&self) -> Piece {
+		let end = match self.end {
+			SegEnd::At(_) => SegEnd::At(self.segment.initial),
+			SegEnd::FullTurn => SegEnd::FullTurn,
+		};
+		Piece { segment: self.segment.reversed(self.end), end }
+	}
+}
+
+/// Which points loops bound, given the number of times they wind around them.
+#[derive(Clone, Copy, Default, Display, Reflect, PartialEq)]
+pub enum FillRule {
+	/// Points wound around any number of times but zero, so that a loop
+	/// overlapping itself in the same direction still bounds the overlap.
+	#[default]
+	NonZero,
+	/// Points wound around an odd number of times, so that overlaps cancel
+	/// out regardless of orientation.
+	EvenOdd,
+}
+
+impl FillRule {
+	pub fn contains(&self, winding_number: i32) -> bool {
+		match self {
+			FillRule::NonZero => winding_number != 0,
+			FillRule::EvenOdd => winding_number % 2 != 0,
+		}
+	}
+}
+
+/// How `resolve` interprets the loops it is given.
+#[derive(Clone, Copy, Default, Reflect, PartialEq)]
+pub struct OverlayOptions {
+	pub fill_rule: FillRule,
+}
+
+/// Boundary of the region bounded by `loops`, which may cross themselves and
+/// each other, e.g. the self-overlapping offset of a concave shape, under
+/// `options.fill_rule`. The result consists of counter-clockwise outer loops
+/// and clockwise holes which do not cross, see `union`.
+pub fn resolve(
+	loops: &[ArcPoly],
+	options: &OverlayOptions,
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay(loops, &[], options.fill_rule, tolerance, |in_loops, _| in_loops)
+}
+
+/// Boundary of the union of the regions bounded by `a` and `b`. Regions are
+/// the points around which their loops wind a non-zero number of times, so
+/// counter-clockwise outer loops and clockwise holes, which is also how the
+/// result is given. Points within `tolerance.merge_distance` are identified
+/// and the sides of the boundaries probed at `tolerance.side_offset`.
+pub fn union(
+	a: &[ArcPoly],
+	b: &[ArcPoly],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay(a, b, FillRule::NonZero, tolerance, |in_a, in_b| in_a || in_b)
+}
+
+/// Boundary of the union of the regions bounded by each of the `operands`,
+/// e.g. the outlines of many strokes, see `union`. All boundaries are cut
+/// against each other in one pass, so unlike folding `union` over them no
+/// boundary is intersected again with the growing result.
+pub fn union_all(
+	operands: &[&[ArcPoly]],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay_all(operands, FillRule::NonZero, tolerance, |inside| {
+		inside.iter().any(|&in_operand| in_operand)
+	})
+}
+
+/// Boundary of the intersection of the regions bounded by `a` and `b`, see
+/// `union`.
+pub fn intersection(
+	a: &[ArcPoly],
+	b: &[ArcPoly],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay(a, b, FillRule::NonZero, tolerance, |in_a, in_b| in_a && in_b)
+}
+
+/// Boundary of the region bounded by `a` with that of `b` removed, see
+/// `union`.
+pub fn difference(
+	a: &[ArcPoly],
+	b: &[ArcPoly],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay(a, b, FillRule::NonZero, tolerance, |in_a, in_b| in_a && !in_b)
+}
+
+/// Boundary of the points in exactly one of the regions bounded by `a` and
+/// `b`, see `union`.
+pub fn symmetric_difference(
+	a: &[ArcPoly],
+	b: &[ArcPoly],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	overlay(a, b, FillRule::NonZero, tolerance, |in_a, in_b| in_a != in_b)
+}
+
+/// Operation combining a region into the result of those before it in
+/// `compose`.
+#[derive(Clone, Copy, Default, Display, Reflect, PartialEq)]
+pub enum BoolOp {
+	#[default]
+	Union,
+	Intersection,
+	Difference,
+	SymmetricDifference,
+}
+
+impl BoolOp {
+	/// Whether a point is in the result, given whether it is in the result so
+	/// far and in the region combined into it.
+	pub fn apply(&self, in_result: bool, in_operand: bool) -> bool {
+		match self {
+			BoolOp::Union => in_result || in_operand,
+			BoolOp::Intersection => in_result && in_operand,
+			BoolOp::Difference => in_result && !in_operand,
+			BoolOp::SymmetricDifference => in_result != in_operand,
+		}
+	}
+}
+
+/// Boundary of the result of combining the regions bounded by each of the
+/// `operands` in turn, starting from the empty region, e.g. a plate with
+/// engraved shapes taken out of it and embossed ones added back on top. The
+/// boundaries are cut against each other once rather than for each step, so
+/// neither runtime nor rounding errors accumulate along the chain. The first
+/// operation is normally `BoolOp::Union`. See `union` for the conventions.
+pub fn compose(
+	operands: &[(&[ArcPoly], BoolOp)],
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	let loops = operands.iter().map(|(loops, _)| *loops).collect_vec();
+	overlay_all(&loops, FillRule::NonZero, tolerance, |inside| {
+		operands
+			.iter()
+			.zip(inside)
+			.fold(false, |in_result, ((_, op), in_operand)| {
+				op.apply(in_result, *in_operand)
+			})
+	})
+}
+
+/// Number of counter-clockwise turns `loops` make around `point` together,
+/// so holes given clockwise count against their outer loop.
+pub fn winding_number(loops: &[ArcPoly], point: &Vec2) -> i32 {
+	loops.iter().map(|l| l.winding_number(point)).sum()
+}
+
+/// Whether `point` is in the region bounded by `loops`, i.e. they wind a
+/// non-zero number of times around it.
+pub fn contains_point(loops: &[ArcPoly], point: &Vec2) -> bool {
+	winding_number(loops, point) != 0
+}
+
+/// The parts of the polyline `path` inside the region bounded by `loops`, or
+/// outside it unless `inside`, as polylines split exactly where the path
+/// crosses the arcs. Parts meeting at a vertex of `path` stay joined, and
+/// crossings within `tolerance.merge_distance` of each other are merged.
+pub fn clip_path(
+	loops: &[ArcPoly],
+	path: &[Vec2],
+	inside: bool,
+	tolerance: &Tolerance,
+) -> Vec<Vec<Vec2>> {
+	let mut parts: Vec<Vec<Vec2>> = vec![];
+	let mut current: Vec<Vec2> = vec![];
+	for (a, b) in path.iter().copied().tuple_windows() {
+		let dir = b - a;
+		let length = dir.length();
+		if length == 0.0 {
+			continue;
+		}
+		let mut params = loops
+			.iter()
+			.flat_map(|l| {
+				(0..l.segments.len()).flat_map(move |i| {
+					let end = SegEnd::in_loop(&l.segments, i);
+					l.segments[i].intersect_line_segment(end, &a, &b)
+				})
+			})
+			.map(|p| (p - a).dot(dir) / length.powi(2))
+			.chain([0.0, 1.0])
+			.sorted_by(f32::total_cmp)
+			.dedup_by(|s, t| (*s - *t).abs() * length < tolerance.merge_distance)
+			.collect_vec();
+		*params.last_mut().unwrap() = 1.0;
+		// The ends are taken as they are, `a + dir` may round off `b`.
+		let point = |t: f32| if t == 1.0 { b } else { a + t * dir };
+		for (s, t) in params.into_iter().tuple_windows() {
+			let (start, end) = (point(s), point(t));
+			if contains_point(loops, &(0.5 * (start + end))) != inside {
+				if !current.is_empty() {
+					parts.push(std::mem::take(&mut current));
+				}
+				continue;
+			}
+			if current.last() != Some(&start) {
+				if !current.is_empty() {
+					parts.push(std::mem::take(&mut current));
+				}
+				current.push(start);
+			}
+			current.push(end);
+		}
+	}
+	if !current.is_empty() {
+		parts.push(current);
+	}
+	parts
+}
+
+/// Boundary of the points for which `op` holds, given whether they are in
+/// the regions of `a` and `b` under `fill_rule`, see `overlay_all`.
+fn overlay(
+	a: &[ArcPoly],
+	b: &[ArcPoly],
+	fill_rule: FillRule,
+	tolerance: &Tolerance,
+	op: impl Fn(bool, bool) -> bool,
+) -> Vec<ArcPoly> {
+	overlay_all(&[a, b], fill_rule, tolerance, |inside| op(inside[0], inside[1]))
+}
+
+/// Boundary of the points for which `op` holds, given whether they are in
+/// the region of each of the `operands` under `fill_rule`. All boundaries are
+/// cut against each other at once, and each piece is kept when `op` differs
+/// on its two sides, oriented to have the result on its left.
+fn overlay_all(
+	operands: &[&[ArcPoly]],
+	fill_rule: FillRule,
+	tolerance: &Tolerance,
+	op: impl Fn(&[bool]) -> bool,
+) -> Vec<ArcPoly> {
+	let merge_distance = tolerance.merge_distance;
+	let pieces = cut_at_intersections(operands, merge_distance);
+	let inside = |p: Vec2| {
+		op(
+			&operands
+				.iter()
+				.map(|loops| fill_rule.contains(winding_number(loops, &p)))
+				.collect_vec(),
+		)
+	};
+	let indices = operands
+		.iter()
+		.map(|loops| loops.iter().map(ArcBvh::from_segments).collect_vec())
+		.collect_vec();
+	let on_operand = |k: usize, p: Vec2| {
+		let near = Rect::from_center_half_size(p, Vec2::splat(merge_distance));
+		operands[k].iter().zip(&indices[k]).any(|(l, index)| {
+			index.query_rect(near).into_iter().any(|i| {
+				let end = SegEnd::in_loop(&l.segments, i);
+				(l.segments[i].closest_point(end, &p) - p).length() < merge_distance
+			})
+		})
+	};
+	let mut kept: Vec<Piece> = vec![];
+	for (k, pieces) in pieces.iter().enumerate() {
+		for piece in pieces {
+			let mid = piece.midpoint();
+			// Shared stretches of boundary are decided by the piece of the
+			// first operand having them.
+			if (0..k).any(|earlier| on_operand(earlier, mid)) {
+				continue;
+			}
+			let normal = tolerance.side_offset
+				* piece.segment.tangent_at(piece.end, &mid).perp();
+			match (inside(mid + normal), inside(mid - normal)) {
+				(true, false) => kept.push(*piece),
+				(false, true) => kept.push(piece.reversed()),
+				_ => {}
+			}
+		}
+	}
+	stitch(kept, merge_distance)
+}
+
+fn pieces(loops: &[ArcPoly]) -> Vec<Piece> {
+	loops
+		.iter()
+		.flat_map(|l| {
+			(0..l.segments.len()).map(move |i| Piece {
+				segment: l.segments[i],
+				end: SegEnd::in_loop(&l.segments, i),
+			})
+		})
+		.collect()
+}
+
+/// The segments of each of the `operands` cut at every point where they meet
+/// another segment, including those of the same loops, which may cross
+/// themselves.
+fn cut_at_intersections(
+	operands: &[&[ArcPoly]],
+	merge_distance: f32,
+) -> Vec<Vec<Piece>> {
+	let pieces = operands.iter().map(|loops| pieces(loops)).collect_vec();
+	let counts = pieces.iter().map(Vec::len).collect_vec();
+	let all = pieces.into_iter().flatten().collect_vec();
+	let bounds = all
+		.iter()
+		.map(|p| p.segment.bounds(p.end).inset(merge_distance))
+		.collect_vec();
+	let mut cuts: Vec<Vec<Vec2>> = vec![vec![]; all.len()];
+	for (i, j) in ArcBvh::new(bounds).overlapping_pairs() {
+		let (p, q) = (&all[i], &all[j]);
+		for contact in
+			p.segment.contacts_with_segment(p.end, &q.segment, q.end, merge_distance)
+		{
+			cuts[i].push(contact.point);
+			cuts[j].push(contact.point);
+		}
+	}
+	let mut all = all.into_iter().zip(cuts);
+	counts
+		.into_iter()
+		.map(|count| {
+			let (pieces, cuts) = all.by_ref().take(count).unzip();
+			cut(pieces, cuts, merge_distance)
+		})
+		.collect()
+}
+
+/// Each piece split at its `cuts`, skipping those within `merge_distance` of
+/// its ends or of each other.
+fn cut(
+	pieces: Vec<Piece>,
+	cuts: Vec<Vec<Vec2>>,
+	merge_distance: f32,
+) -> Vec<Piece> {
+	pieces
+		.into_iter()
+		.zip(cuts)
+		.flat_map(|(piece, cuts)| {
+			let Piece { segment, end } = piece;
+			let mut points = vec![segment.initial];
+			let fraction = |p: &Vec2| segment.fraction_at(end, p);
+			for p in
+				cuts.into_iter().sorted_by(|p, q| fraction(p).total_cmp(&fraction(q)))
+			{
+				if (p - *points.last().unwrap()).length() >= merge_distance
+					&& (p - piece.end_point()).length() >= merge_distance
+				{
+					points.push(p);
+				}
+			}
+			if points.len() == 1 {
+				return vec![piece];
+			}
+			points.push(piece.end_point());
+			points
+				.into_iter()
+				.tuple_windows()
+				.map(|(initial, end)| Piece {
+					segment: Segment { initial, ..segment },
+					end: SegEnd::At(end),
+				})
+				.collect_vec()
+		})
+		.collect()
+}
+
+/// Joins pieces end to start into closed loops, dropping chains which do
+/// not close.
+fn stitch(mut pieces: Vec<Piece>, merge_distance: f32) -> Vec<ArcPoly> {
+	let mut loops: Vec<ArcPoly> = vec![];
+	while let Some(first) = pieces.pop() {
+		let mut chain = vec![first];
+		loop {
+			let end = chain.last().unwrap().end_point();
+			if (end - first.segment.initial).length() < merge_distance {
+				loops.push(merged(&chain, merge_distance));
+				break;
+			}
+			let gap = |p: &Piece| (p.segment.initial - end).length();
+			match pieces
+				.iter()
+				.position_min_by(|p, q| gap(p).total_cmp(&gap(q)))
+				.filter(|k| gap(&pieces[*k]) < merge_distance)
+			{
+				Some(k) => chain.push(pieces.swap_remove(k)),
+				None => break,
+			}
+		}
+	}
+	loops
+}
+
+/// Whether `b`, starting where `a` ends, continues the same circle or line.
+fn continues(a: &Piece, b: &Piece, merge_distance: f32) -> bool {
+	a.segment.bend == b.segment.bend
+		&& match a.segment.bend {
+			Bend::Straight => {
+				let (u, v) = (
+					a.end_point() - a.segment.initial,
+					b.end_point() - b.segment.initial,
+				);
+				u.perp_dot(v).abs() <= merge_distance * u.length().max(v.length())
+					&& u.dot(v) > 0.0
+			}
+			_ => a.segment.center.approx_eq(&b.segment.center, merge_distance),
+		}
+}
+
+/// The loop through `chain`, joining pieces which continue each other.
+fn merged(chain: &[Piece], merge_distance: f32) -> ArcPoly {
+	let mut joined: Vec<Piece> = vec![];
+	for piece in chain {
+		match joined.last_mut() {
+			Some(last) if continues(last, piece, merge_distance) => {
+				last.end = piece.end
+			}
+			_ => joined.push(*piece),
+		}
+	}
+	if joined.len() > 1
+		&& continues(joined.last().unwrap(), &joined[0], merge_distance)
+	{
+		joined.remove(0);
+		joined.rotate_right(1);
+	}
+	ArcPoly { segments: joined.into_iter().map(|p| p.segment).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::prelude::default;
+
+	use super::*;
+	use crate::{
+		geom::arc_poly::ArcPolyGenInput,
+		math::{Transform2, Xform2},
+	};
+
+	#[test]
+	fn clip_path_keeps_vertices_inside_joined() {
+		let circle = [ArcPoly::circle(Vec2::ZERO, 1e5)];
+		// Going back from `x = 1` to `x = 1e-9` rounds to going back by 1.
+		let path = [Vec2::new(1.0, 1.0), Vec2::new(1e-9, 1.0), Vec2::new(1.0, 2.0)];
+		let parts = clip_path(&circle, &path, true, &Tolerance::default());
+		assert_eq!(parts, [path.to_vec()]);
+	}
+
+	#[test]
+	fn areas_and_perimeters_add_up_across_operations() {
+		let tolerance = Tolerance::default();
+		let total = |loops: Vec<ArcPoly>| {
+			let area = loops.iter().map(ArcPoly::area).sum::<f32>();
+			(area, loops.iter().map(ArcPoly::perimeter).sum::<f32>())
+		};
+		let mut pairs = vec![(
+			ArcPoly::circle(Vec2::ZERO, 50.0),
+			ArcPoly::rectangle(Rect::new(20.0, -30.0, 120.0, 30.0)),
+		)];
+		for random_seed in 0..4 {
+			let input = ArcPolyGenInput { random_seed, ..default() };
+			let poly = ArcPoly::from_gen_input(&input);
+			let mut moved = poly.clone();
+			moved.apply_xform(&Xform2::from_translation(Vec2::new(37.0, 11.0)));
+			pairs.push((poly, moved));
+		}
+		for (a, b) in pairs {
+			let (a, b) = ([a], [b]);
+			// Generated loops may cross themselves, so measure their regions.
+			let (area_a, perimeter_a) = total(resolve(&a, &default(), &tolerance));
+			let (area_b, perimeter_b) = total(resolve(&b, &default(), &tolerance));
+			let (area_or, perimeter_or) = total(union(&a, &b, &tolerance));
+			let (area_and, perimeter_and) = total(intersection(&a, &b, &tolerance));
+			let (area_not, _) = total(difference(&a, &b, &tolerance));
+			let (area_xor, _) = total(symmetric_difference(&a, &b, &tolerance));
+			let close = |found: f32, expected: f32| {
+				(found - expected).abs() <= 1e-3 * expected.abs().max(1.0)
+			};
+			assert!(close(area_or + area_and, area_a + area_b));
+			assert!(close(perimeter_or + perimeter_and, perimeter_a + perimeter_b));
+			assert!(close(area_not, area_a - area_and));
+			assert!(close(area_xor, area_or - area_and));
+		}
+	}
+}
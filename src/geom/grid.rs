@@ -0,0 +1,77 @@
+use bevy::{
+	math::{Rect, Vec2},
+	reflect::Reflect,
+};
+use derive_more::Display;
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum SampleKind {
+	/// 1 inside, 0 outside and 0.5 on the boundary.
+	Containment,
+	/// Signed distance to the boundary, negative inside.
+	Distance,
+}
+
+/// Row major samples at `origin + step * (x, y)`.
+#[derive(Clone)]
+pub struct Grid<T> {
+	pub origin: Vec2,
+	pub step: f32,
+	pub width: usize,
+	pub height: usize,
+	pub values: Vec<T>,
+}
+
+impl<T> Grid<T> {
+	/// Grid covering `bounds` with samples `step` apart, starting at its
+	/// minimum corner. A `step` which is not positive and finite gives an
+	/// empty grid.
+	pub fn from_fn(
+		bounds: Rect,
+		step: f32,
+		f: impl FnOnce(&[Vec2]) -> Vec<T>,
+	) -> Self {
+		let (width, height) = if step.is_finite() && step > 0.0 {
+			let cells = (bounds.size() / step).floor();
+			(cells.x as usize + 1, cells.y as usize + 1)
+		} else {
+			(0, 0)
+		};
+		let mut grid =
+			Grid { origin: bounds.min, step, width, height, values: vec![] };
+		grid.values = f(&grid.positions());
+		grid
+	}
+
+	pub fn position(&self, x: usize, y: usize) -> Vec2 {
+		self.origin + self.step * Vec2::new(x as f32, y as f32)
+	}
+
+	pub fn positions(&self) -> Vec<Vec2> {
+		(0..self.height)
+			.flat_map(|y| (0..self.width).map(move |x| self.position(x, y)))
+			.collect()
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+		(x < self.width).then(|| self.values.get(y * self.width + x)).flatten()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn from_fn_with_invalid_steps_is_empty() {
+		let bounds = Rect::new(0.0, 0.0, 10.0, 5.0);
+		for step in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+			let grid = Grid::from_fn(bounds, step, |points| points.to_vec());
+			assert_eq!((grid.width, grid.height), (0, 0));
+			assert!(grid.values.is_empty() && grid.get(0, 0).is_none());
+		}
+		let grid = Grid::from_fn(bounds, 2.5, |points| points.to_vec());
+		assert_eq!((grid.width, grid.height), (5, 3));
+		assert_eq!(grid.get(4, 2), Some(&Vec2::new(10.0, 5.0)));
+	}
+}
@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use bevy::math::{Rect, Vec2};
+
+use super::arc_poly::ArcPoly;
+
+/// Largest number of items kept in a leaf.
+const LEAF_SIZE: usize = 4;
+
+/// Bounding volume hierarchy over axis aligned rectangles, typically the
+/// bounds of segments, answering which of them may be hit by a point, a box
+/// or a ray and which pairs of them overlap. Items are referred to by their
+/// index in the rectangles it was built from. Rectangles touching along an
+/// edge count as overlapping.
+#[derive(Clone, Default)]
+pub struct ArcBvh {
+	bounds: Vec<Rect>,
+	/// Item indices, grouped so that each leaf covers a range of them.
+	order: Vec<usize>,
+	/// Nodes with the root, if any, first.
+	nodes: Vec<Node>,
+}
+
+#[derive(Clone)]
+struct Node {
+	bounds: Rect,
+	kind: NodeKind,
+}
+
+#[derive(Clone)]
+enum NodeKind {
+	Leaf(Range<usize>),
+	Branch(usize, usize),
+}
+
+impl ArcBvh {
+	pub fn new(bounds: Vec<Rect>) -> ArcBvh {
+		let mut bvh =
+			ArcBvh { order: (0..bounds.len()).collect(), bounds, nodes: vec![] };
+		if !bvh.bounds.is_empty() {
+			bvh.build(0..bvh.order.len());
+		}
+		bvh
+	}
+
+	/// Index over the segments of `poly`, see `ArcPoly::segment_bounds`.
+	pub fn from_segments(poly: &ArcPoly) -> ArcBvh {
+		ArcBvh::new(poly.segment_bounds().collect())
+	}
+
+	/// Items whose bounds contain `point`.
+	pub fn query_point(&self, point: Vec2) -> Vec<usize> {
+		self.query_rect(Rect::from_corners(point, point))
+	}
+
+	/// Items whose bounds overlap `rect`.
+	pub fn query_rect(&self, rect: Rect) -> Vec<usize> {
+		let mut items = vec![];
+		self.visit(|bounds| overlaps(bounds, &rect), &mut items);
+		items
+	}
+
+	/// Items whose bounds are hit by the ray from `origin` along `dir`.
+	pub fn query_ray(&self, origin: Vec2, dir: Vec2) -> Vec<usize> {
+		let mut items = vec![];
+		self.visit(|bounds| ray_hits(bounds, origin, dir), &mut items);
+		items
+	}
+
+	/// Pairs `(i, j)` with `i < j` of items whose bounds overlap.
+	pub fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+		let mut pairs = vec![];
+		if !self.nodes.is_empty() {
+			self.pairs_within(0, &mut pairs);
+		}
+		pairs
+	}
+
+	/// Splits `range` of `order` at the median along the longer side of the
+	/// bounds of the centers, returning the index of the new node.
+	fn build(&mut self, range: Range<usize>) -> usize {
+		let bounds = self.order[range.clone()]
+			.iter()
+			.map(|&i| self.bounds[i])
+			.reduce(|a, b| a.union(b))
+			.unwrap();
+		let idx = self.nodes.len();
+		self.nodes.push(Node { bounds, kind: NodeKind::Leaf(range.clone()) });
+		if range.len() <= LEAF_SIZE {
+			return idx;
+		}
+		let size = bounds.size();
+		let axis = if size.x >= size.y { 0 } else { 1 };
+		let center = |i: usize| self.bounds[i].center()[axis];
+		let mid = range.start + range.len() / 2;
+		let mut items = self.order[range.clone()].to_vec();
+		items.sort_by(|&i, &j| center(i).total_cmp(&center(j)));
+		self.order[range.clone()].copy_from_slice(&items);
+		let left = self.build(range.start..mid);
+		let right = self.build(mid..range.end);
+		self.nodes[idx].kind = NodeKind::Branch(left, right);
+		idx
+	}
+
+	fn visit(&self, hit: impl Fn(&Rect) -> bool, items: &mut Vec<usize>) {
+		let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+		while let Some(idx) = stack.pop() {
+			let node = &self.nodes[idx];
+			if !hit(&node.bounds) {
+				continue;
+			}
+			match &node.kind {
+				NodeKind::Leaf(range) => items.extend(
+					self.order[range.clone()].iter().filter(|&&i| hit(&self.bounds[i])),
+				),
+				NodeKind::Branch(left, right) => stack.extend([*left, *right]),
+			}
+		}
+	}
+
+	fn pairs_within(&self, idx: usize, pairs: &mut Vec<(usize, usize)>) {
+		match self.nodes[idx].kind.clone() {
+			NodeKind::Leaf(range) => {
+				for (k, &i) in self.order[range.clone()].iter().enumerate() {
+					for &j in &self.order[range.start + k + 1..range.end] {
+						self.push_if_overlapping(i, j, pairs);
+					}
+				}
+			}
+			NodeKind::Branch(left, right) => {
+				self.pairs_within(left, pairs);
+				self.pairs_within(right, pairs);
+				self.pairs_between(left, right, pairs);
+			}
+		}
+	}
+
+	fn pairs_between(&self, a: usize, b: usize, pairs: &mut Vec<(usize, usize)>) {
+		let (node_a, node_b) = (&self.nodes[a], &self.nodes[b]);
+		if !overlaps(&node_a.bounds, &node_b.bounds) {
+			return;
+		}
+		match (&node_a.kind, &node_b.kind) {
+			(NodeKind::Leaf(range_a), NodeKind::Leaf(range_b)) => {
+				for &i in &self.order[range_a.clone()] {
+					for &j in &self.order[range_b.clone()] {
+						self.push_if_overlapping(i, j, pairs);
+					}
+				}
+			}
+			(NodeKind::Branch(left, right), _) => {
+				self.pairs_between(*left, b, pairs);
+				self.pairs_between(*right, b, pairs);
+			}
+			(_, NodeKind::Branch(left, right)) => {
+				self.pairs_between(a, *left, pairs);
+				self.pairs_between(a, *right, pairs);
+			}
+		}
+	}
+
+	fn push_if_overlapping(
+		&self,
+		i: usize,
+		j: usize,
+		pairs: &mut Vec<(usize, usize)>,
+	) {
+		if overlaps(&self.bounds[i], &self.bounds[j]) {
+			pairs.push((i.min(j), i.max(j)));
+		}
+	}
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+	a.min.cmple(b.max).all() && b.min.cmple(a.max).all()
+}
+
+/// Slab test of the ray from `origin` along `dir` against `rect`.
+fn ray_hits(rect: &Rect, origin: Vec2, dir: Vec2) -> bool {
+	let (mut near, mut far) = (0.0f32, f32::INFINITY);
+	for axis in 0..2 {
+		let (o, d) = (origin[axis], dir[axis]);
+		let (min, max) = (rect.min[axis], rect.max[axis]);
+		if d == 0.0 {
+			if o < min || o > max {
+				return false;
+			}
+			continue;
+		}
+		let (t0, t1) = ((min - o) / d, (max - o) / d);
+		near = near.max(t0.min(t1));
+		far = far.min(t0.max(t1));
+	}
+	near <= far
+}
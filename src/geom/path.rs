@@ -0,0 +1,213 @@
+use bevy::{gizmos::gizmos::Gizmos, math::Vec2, render::color::Color};
+
+use crate::math::Tolerance;
+
+use super::{
+	arc_poly::ArcPoly,
+	segment::{draw_segment, Bend, SegEnd, Segment},
+};
+
+/// Open chain of segments, each ending where the next one starts and the
+/// last one at `end`, e.g. a text baseline or an engraving stroke.
+#[derive(Clone, Default)]
+pub struct ArcPath {
+	pub segments: Vec<Segment>,
+	pub end: Vec2,
+}
+
+/// Outline of a path stroked with round caps, kept in its parts rather than
+/// merged into a ring. `left` and `right` run in the direction of the path,
+/// while the caps run counter-clockwise around its end points, so that
+/// `right`, `end_cap`, `left` reversed and `start_cap` form a closed loop.
+#[derive(Clone, Default)]
+pub struct StrokeParts {
+	pub left: ArcPath,
+	pub right: ArcPath,
+	pub start_cap: ArcPath,
+	pub end_cap: ArcPath,
+}
+
+/// Offset of a single segment together with the direction of the original
+/// segment at both of its ends, which decides how neighbours are joined.
+struct OffsetPiece {
+	segment: Segment,
+	end: Vec2,
+	joint: Vec2,
+	tangent_in: Vec2,
+	tangent_out: Vec2,
+}
+
+impl ArcPath {
+	pub fn start(&self) -> Vec2 {
+		self.segments.first().map_or(self.end, |s| s.initial)
+	}
+
+	pub fn next_initial(&self, idx: usize) -> Vec2 {
+		self.segments.get(idx + 1).map_or(self.end, |s| s.initial)
+	}
+
+	/// End of the segment at `idx`, which never makes a full turn in an open
+	/// path.
+	pub fn end_of(&self, idx: usize) -> SegEnd {
+		SegEnd::At(self.next_initial(idx))
+	}
+
+	pub fn length(&self) -> f32 {
+		(0..self.segments.len())
+			.map(|i| self.segments[i].length(self.end_of(i)))
+			.sum()
+	}
+
+	/// The same path travelled from `end` back to the start.
+	pub fn reversed(&self) -> ArcPath {
+		let segments = (0..self.segments.len())
+			.rev()
+			.map(|i| self.segments[i].reversed(self.end_of(i)))
+			.collect();
+		ArcPath { segments, end: self.start() }
+	}
+
+	/// Path at `distance` to the left of this one, or to the right when
+	/// negative. Arcs keep their centers, and neighbouring offsets are joined
+	/// with round joins around the original joint where they part and cut
+	/// at their intersection where they overlap. Loops of the offset which
+	/// are not between neighbours, e.g. of arcs tighter than `distance`, are
+	/// kept.
+	pub fn offset(&self, distance: f32, tolerance: &Tolerance) -> ArcPath {
+		let mut segments: Vec<Segment> = vec![];
+		let mut end = self.start() + distance * self.start_normal();
+		let mut previous: Option<OffsetPiece> = None;
+		for i in 0..self.segments.len() {
+			let Some(mut piece) = offset_piece(
+				&self.segments[i],
+				&self.next_initial(i),
+				distance,
+				tolerance,
+			) else {
+				continue;
+			};
+			if let Some(last) = &previous {
+				let turn = last.tangent_out.perp_dot(piece.tangent_in);
+				if end.distance(piece.segment.initial) <= tolerance.merge_distance {
+					piece.segment.initial = end;
+				} else if turn * distance < 0.0 {
+					segments.push(Segment::from_center_start(
+						last.joint,
+						end,
+						turn > 0.0,
+					));
+				} else {
+					let last_segment = segments.last().unwrap();
+					match last_segment
+						.intersect_segment(
+							SegEnd::At(end),
+							&piece.segment,
+							SegEnd::At(piece.end),
+						)
+						.pop()
+					{
+						Some(crossing) => piece.segment.initial = crossing,
+						None => {
+							segments.push(Segment::straight(end, piece.segment.initial))
+						}
+					}
+				}
+			}
+			segments.push(piece.segment);
+			end = piece.end;
+			previous = Some(piece);
+		}
+		ArcPath { segments, end }
+	}
+
+	/// Outline of the path stroked at `half_width` on either side, see
+	/// `StrokeParts`.
+	pub fn stroke(&self, half_width: f32, tolerance: &Tolerance) -> StrokeParts {
+		if self.segments.is_empty() {
+			return StrokeParts::default();
+		}
+		let left = self.offset(half_width, tolerance);
+		let right = self.offset(-half_width, tolerance);
+		let start_cap = ArcPath {
+			segments: vec![Segment::from_center_start(
+				self.start(),
+				left.start(),
+				true,
+			)],
+			end: right.start(),
+		};
+		let end_cap = ArcPath {
+			segments: vec![Segment::from_center_start(self.end, right.end, true)],
+			end: left.end,
+		};
+		StrokeParts { left, right, start_cap, end_cap }
+	}
+
+	pub fn draw(&self, gizmos: &mut Gizmos, color: &Color) {
+		for i in 0..self.segments.len() {
+			draw_segment(&self.segments[i], self.end_of(i), gizmos, color);
+		}
+	}
+
+	/// Unit normal to the left of the path at its start, or zero for an empty
+	/// path.
+	fn start_normal(&self) -> Vec2 {
+		self
+			.segments
+			.first()
+			.map_or(Vec2::ZERO, |s| s.tangent_at(self.end_of(0), &s.initial).perp())
+	}
+}
+
+impl StrokeParts {
+	/// The parts joined into a single counter-clockwise loop, which may
+	/// intersect itself where the path comes closer to itself than twice the
+	/// half width.
+	pub fn outline(&self) -> ArcPoly {
+		let segments = self
+			.right
+			.segments
+			.iter()
+			.chain(self.end_cap.segments.iter())
+			.chain(self.left.reversed().segments.iter())
+			.chain(self.start_cap.segments.iter())
+			.copied()
+			.collect();
+		ArcPoly { segments }
+	}
+}
+
+/// Offset of `segment`, ending at `next_initial`, by `distance` to its left,
+/// or `None` for segments too short to have a direction or offsets shrunk to
+/// a point.
+fn offset_piece(
+	segment: &Segment,
+	next_initial: &Vec2,
+	distance: f32,
+	tolerance: &Tolerance,
+) -> Option<OffsetPiece> {
+	let segment_end = SegEnd::At(*next_initial);
+	if segment.length(segment_end) <= tolerance.min_length {
+		return None;
+	}
+	let tangent_in = segment.tangent_at(segment_end, &segment.initial);
+	let tangent_out = segment.tangent_at(segment_end, next_initial);
+	let initial = segment.initial + distance * tangent_in.perp();
+	let end = *next_initial + distance * tangent_out.perp();
+	let offset = match segment.bend {
+		Bend::Straight => {
+			Segment { initial, center: 0.5 * (initial + end), ..*segment }
+		}
+		_ => Segment { initial, ..*segment },
+	};
+	if offset.bend != Bend::Straight && offset.radius() <= tolerance.min_radius {
+		return None;
+	}
+	Some(OffsetPiece {
+		segment: offset,
+		end,
+		joint: *next_initial,
+		tangent_in,
+		tangent_out,
+	})
+}
@@ -0,0 +1,514 @@
+use bevy::{math::Vec2, prelude::default};
+use itertools::Itertools;
+
+use crate::{
+	math::{FloatBackend, Tolerance},
+	util::Cancellation,
+};
+
+use super::{
+	arc_poly::{
+		ArcPoly, LoopIdx, Orientation, SegIdx, ShrinkError, ValidationIssue,
+	},
+	boolean::{self, BoolOp, OverlayOptions},
+	path::ArcPath,
+	segment::Segment,
+};
+
+/// An outer boundary with the holes cut out of it and the islands inside
+/// those holes, which are regions in their own right. Outer boundaries are
+/// counter-clockwise and holes clockwise, so the loops together wind once
+/// around exactly the points of the region, unless another convention is
+/// chosen with `normalize_orientation`.
+#[derive(Clone, Default)]
+pub struct Region {
+	pub outer: ArcPoly,
+	pub holes: Vec<ArcPoly>,
+	pub islands: Vec<Region>,
+}
+
+impl Region {
+	/// Nests `loops`, e.g. the result of a boolean operation or an offset,
+	/// into regions by containment, regardless of their orientations. Loops
+	/// are assumed not to cross each other.
+	pub fn from_loops(loops: Vec<ArcPoly>) -> Vec<Region> {
+		let mut regions: Vec<Region> = vec![];
+		for poly in loops
+			.into_iter()
+			.sorted_by(|a, b| b.area().abs().total_cmp(&a.area().abs()))
+		{
+			insert(&mut regions, poly);
+		}
+		regions
+	}
+
+	/// Regions bounded by `loops`, which unlike for `from_loops` may cross
+	/// themselves and each other, under the fill rule of `options`.
+	pub fn resolve(
+		loops: &[ArcPoly],
+		options: &OverlayOptions,
+		tolerance: &Tolerance,
+	) -> Vec<Region> {
+		Region::from_loops(boolean::resolve(loops, options, tolerance))
+	}
+
+	/// Regions resulting from combining `regions` in turn, see
+	/// `boolean::compose`.
+	pub fn compose(
+		regions: &[(Region, BoolOp)],
+		tolerance: &Tolerance,
+	) -> Vec<Region> {
+		let loops = regions.iter().map(|(region, _)| region.loops()).collect_vec();
+		let operands = loops
+			.iter()
+			.zip(regions)
+			.map(|(loops, (_, op))| (loops.as_slice(), *op))
+			.collect_vec();
+		Region::from_loops(boolean::compose(&operands, tolerance))
+	}
+
+	/// Inward offset of the region by `amount`, as for pocketing around
+	/// islands: the outer boundary shrinks as by `ArcPoly::shrunk` while the
+	/// holes grow, and the material is cut through where they meet each
+	/// other or the outer boundary. Islands shrink in their own right. Holes
+	/// grow by the same wavefront propagation run on them oriented
+	/// clockwise, so they have to consist of arcs it supports that way, such
+	/// as circles. Fails, before offsetting anything, if the outer boundary
+	/// or a hole is a loop `ArcPoly::check_shrinkable` rejects.
+	pub fn shrunk(
+		&self,
+		amount: f32,
+		tolerance: &Tolerance,
+	) -> Result<Vec<Region>, ShrinkError> {
+		let oriented = |poly: &ArcPoly, orientation: Orientation| {
+			let mut poly = poly.clone();
+			poly.normalize_orientation(orientation);
+			poly
+		};
+		let outer_loop = oriented(&self.outer, Orientation::CounterClockwise);
+		let hole_loops = self
+			.holes
+			.iter()
+			.map(|hole| oriented(hole, Orientation::Clockwise))
+			.collect_vec();
+		for poly in std::iter::once(&outer_loop).chain(&hole_loops) {
+			poly.check_shrinkable(tolerance)?;
+		}
+		let offset = |poly: &ArcPoly| {
+			poly.shrunk_with(
+				amount,
+				&FloatBackend::default(),
+				tolerance,
+				&Cancellation::default(),
+				&mut |_| {},
+			)
+		};
+		let shrunk = offset(&outer_loop)?;
+		let mut holes = vec![];
+		for hole in &hole_loops {
+			holes.extend(offset(hole)?.into_iter().map(outer));
+		}
+		let mut regions =
+			Region::from_loops(boolean::difference(&shrunk, &holes, tolerance));
+		for island in &self.islands {
+			regions.extend(island.shrunk(amount, tolerance)?);
+		}
+		Ok(regions)
+	}
+
+	/// Morphological opening by a disc of `radius`, i.e. the erosion followed
+	/// by the dilation by it, which removes the parts narrower than twice
+	/// `radius`, such as slivers, and rounds convex corners. A `radius` which
+	/// is not positive leaves the region as it is.
+	pub fn open(&self, radius: f32, tolerance: &Tolerance) -> Vec<Region> {
+		if radius.is_nan() || radius <= 0.0 {
+			return vec![self.clone()];
+		}
+		let eroded = eroded(&self.loops(), radius, tolerance);
+		Region::from_loops(dilated(&eroded, radius, tolerance))
+	}
+
+	/// Morphological closing by a disc of `radius`, i.e. the dilation followed
+	/// by the erosion by it, which fills gaps and holes narrower than twice
+	/// `radius`, such as pinholes, and rounds concave corners. A `radius`
+	/// which is not positive leaves the region as it is.
+	pub fn close(&self, radius: f32, tolerance: &Tolerance) -> Vec<Region> {
+		if radius.is_nan() || radius <= 0.0 {
+			return vec![self.clone()];
+		}
+		let dilated = dilated(&self.loops(), radius, tolerance);
+		Region::from_loops(eroded(&dilated, radius, tolerance))
+	}
+
+	/// Regions of the points within `radius` of any of `points`, i.e. the
+	/// union of the discs around them. A `radius` which is not positive gives
+	/// no regions.
+	pub fn dilate_points(
+		points: &[Vec2],
+		radius: f32,
+		tolerance: &Tolerance,
+	) -> Vec<Region> {
+		if radius.is_nan() || radius <= 0.0 {
+			return vec![];
+		}
+		let discs =
+			points.iter().map(|&p| ArcPoly::circle(p, radius)).collect_vec();
+		Region::from_loops(union_each(&discs, tolerance))
+	}
+
+	/// Regions of the points within `radius` of the open polyline through
+	/// `points`, i.e. the union of the outlines of its pieces stroked at
+	/// `radius`, or the disc around its point if it has no length. A `radius`
+	/// which is not positive gives no regions.
+	pub fn dilate_polyline(
+		points: &[Vec2],
+		radius: f32,
+		tolerance: &Tolerance,
+	) -> Vec<Region> {
+		if radius.is_nan() || radius <= 0.0 {
+			return vec![];
+		}
+		let mut outlines = points
+			.iter()
+			.tuple_windows()
+			.filter(|(a, b)| a.distance(**b) > tolerance.min_length)
+			.map(|(&a, &b)| {
+				let path = ArcPath { segments: vec![Segment::straight(a, b)], end: b };
+				path.stroke(radius, tolerance).outline()
+			})
+			.collect_vec();
+		if outlines.is_empty() {
+			outlines.extend(points.first().map(|&p| ArcPoly::circle(p, radius)));
+		}
+		Region::from_loops(union_each(&outlines, tolerance))
+	}
+
+	/// All loops of the region, outer boundaries before holes and then those
+	/// of the islands, as taken by the functions of `boolean`.
+	pub fn loops(&self) -> Vec<ArcPoly> {
+		let mut loops = vec![self.outer.clone()];
+		loops.extend(self.holes.iter().cloned());
+		loops.extend(self.islands.iter().flat_map(|island| island.loops()));
+		loops
+	}
+
+	/// Orients outer boundaries, including those of the islands, as `outer`
+	/// and holes the opposite way, as exporters and tessellators expecting a
+	/// fixed winding convention require.
+	pub fn normalize_orientation(&mut self, outer: Orientation) {
+		self.outer.normalize_orientation(outer);
+		for hole in self.holes.iter_mut() {
+			hole.normalize_orientation(outer.opposite());
+		}
+		for island in self.islands.iter_mut() {
+			island.normalize_orientation(outer);
+		}
+	}
+
+	/// `ArcPoly::diagnose` of every loop, indexed as in `loops`, together
+	/// with holes oriented like the outer boundary and outer boundaries of
+	/// islands oriented unlike it.
+	pub fn diagnose(
+		&self,
+		tolerance: &Tolerance,
+	) -> Vec<(LoopIdx, ValidationIssue)> {
+		self
+			.oriented_loops(self.outer.orientation())
+			.into_iter()
+			.enumerate()
+			.flat_map(|(k, (poly, expected))| {
+				let mut issues = poly.diagnose(tolerance);
+				let found = poly.orientation();
+				if !poly.segments.is_empty() && found != expected {
+					issues.push(ValidationIssue::WrongOrientation { found, expected });
+				}
+				issues.into_iter().map(move |issue| (LoopIdx(k), issue))
+			})
+			.collect()
+	}
+
+	pub fn contains_point(&self, point: &Vec2) -> bool {
+		(self.outer.contains_point(point)
+			&& !self.holes.iter().any(|hole| hole.contains_point(point)))
+			|| self.islands.iter().any(|island| island.contains_point(point))
+	}
+
+	/// Area of the points of the region, which is positive whatever the
+	/// orientation of its loops.
+	pub fn area(&self) -> f32 {
+		self.outer.area().abs()
+			- self.holes.iter().map(|hole| hole.area().abs()).sum::<f32>()
+			+ self.islands.iter().map(|island| island.area()).sum::<f32>()
+	}
+
+	/// The loops in the order of `loops`, each with the orientation expected
+	/// when outer boundaries are oriented as `outer`.
+	fn oriented_loops(&self, outer: Orientation) -> Vec<(&ArcPoly, Orientation)> {
+		let mut loops = vec![(&self.outer, outer)];
+		loops.extend(self.holes.iter().map(|hole| (hole, outer.opposite())));
+		loops.extend(self.islands.iter().flat_map(|i| i.oriented_loops(outer)));
+		loops
+	}
+}
+
+/// The points within `radius` of the boundary of the region bounded by
+/// `loops`, as the loops of their union with it.
+fn dilated(
+	loops: &[ArcPoly],
+	radius: f32,
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	boolean::union(loops, &boundary_band(loops, radius, tolerance), tolerance)
+}
+
+/// The points of the region bounded by `loops` farther than `radius` from
+/// its boundary, as loops.
+fn eroded(
+	loops: &[ArcPoly],
+	radius: f32,
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	boolean::difference(
+		loops,
+		&boundary_band(loops, radius, tolerance),
+		tolerance,
+	)
+}
+
+/// Loops bounding the points within `radius` of any of `loops`: the union of
+/// the outlines of their joints stroked at `radius`, each from halfway along
+/// the segment before it to halfway along the one after it, so that the
+/// joins are made within a stroke and neighbouring strokes meet where the
+/// loop is smooth. Each outline is an operand of its own, see `union_all`.
+fn boundary_band(
+	loops: &[ArcPoly],
+	radius: f32,
+	tolerance: &Tolerance,
+) -> Vec<ArcPoly> {
+	let mut outlines = vec![];
+	for poly in loops {
+		let halves = poly
+			.indices()
+			.flat_map(|i| {
+				let end = poly.end_of(i);
+				let [head, tail] = poly[i].split_at(end, 0.5);
+				[(head, tail.initial), (tail, poly[i].end_point(end))]
+			})
+			.collect_vec();
+		for k in (0..halves.len()).step_by(2) {
+			let (before, _) = halves[(k + halves.len() - 1) % halves.len()];
+			let (after, end) = halves[k];
+			let path = ArcPath { segments: vec![before, after], end };
+			outlines.push(path.stroke(radius, tolerance).outline());
+		}
+	}
+	outlines.retain(|outline| !outline.segments.is_empty());
+	union_each(&outlines, tolerance)
+}
+
+/// Boundary of the union of the regions bounded by each of `loops` on its
+/// own, see `boolean::union_all`.
+fn union_each(loops: &[ArcPoly], tolerance: &Tolerance) -> Vec<ArcPoly> {
+	let operands = loops.iter().map(std::slice::from_ref).collect_vec();
+	boolean::union_all(&operands, tolerance)
+}
+
+/// Adds `poly` to the innermost of `regions` containing it, as a hole or as
+/// an island depending on whether it lies in one of its holes, or as a new
+/// region if none contains it. Containers must have been inserted first.
+fn insert(regions: &mut Vec<Region>, poly: ArcPoly) {
+	let Some(point) = sample_point(&poly) else {
+		return;
+	};
+	match regions.iter_mut().find(|r| r.outer.contains_point(&point)) {
+		Some(region) => {
+			if region.islands.iter().any(|i| i.outer.contains_point(&point)) {
+				insert(&mut region.islands, poly);
+			} else if region.holes.iter().any(|h| h.contains_point(&point)) {
+				region.islands.push(Region { outer: outer(poly), ..default() });
+			} else {
+				let mut hole = poly;
+				hole.normalize_orientation(Orientation::Clockwise);
+				region.holes.push(hole);
+			}
+		}
+		None => regions.push(Region { outer: outer(poly), ..default() }),
+	}
+}
+
+fn sample_point(poly: &ArcPoly) -> Option<Vec2> {
+	let first = poly.segments.first()?;
+	Some(first.point_at(poly.end_of(SegIdx(0)), 0.5))
+}
+
+fn outer(mut poly: ArcPoly) -> ArcPoly {
+	poly.normalize_orientation(Orientation::CounterClockwise);
+	poly
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::math::Rect;
+
+	use super::*;
+	use crate::geom::segment::Bend;
+
+	#[test]
+	fn shrunk_rejects_unsupported_outer_boundary() {
+		let region = Region {
+			outer: ArcPoly::rectangle(Rect::new(0.0, 0.0, 100.0, 50.0)),
+			..default()
+		};
+		assert!(matches!(
+			region.shrunk(5.0, &Tolerance::default()),
+			Err(ShrinkError::Unsupported(_, Bend::Straight))
+		));
+	}
+
+	#[test]
+	fn shrunk_grows_holes() {
+		let region = Region {
+			outer: ArcPoly::circle(Vec2::ZERO, 100.0),
+			holes: vec![ArcPoly::circle(Vec2::ZERO, 20.0).reversed()],
+			..default()
+		};
+		let shrunk = region.shrunk(10.0, &Tolerance::default()).unwrap();
+		assert_eq!(shrunk.len(), 1);
+		let expected = std::f32::consts::PI * (90.0f32.powi(2) - 30.0f32.powi(2));
+		assert!((shrunk[0].area() - expected).abs() < 1e-3 * expected);
+	}
+
+	#[test]
+	fn area_does_not_depend_on_orientation() {
+		let mut region = Region {
+			outer: ArcPoly::circle(Vec2::ZERO, 100.0),
+			holes: vec![ArcPoly::circle(Vec2::ZERO, 50.0).reversed()],
+			islands: vec![Region {
+				outer: ArcPoly::circle(Vec2::ZERO, 20.0),
+				..default()
+			}],
+		};
+		let expected = std::f32::consts::PI
+			* (100.0f32.powi(2) - 50.0f32.powi(2) + 20.0f32.powi(2));
+		assert!((region.area() - expected).abs() < 1e-3 * expected);
+		region.normalize_orientation(Orientation::Clockwise);
+		assert!((region.area() - expected).abs() < 1e-3 * expected);
+	}
+
+	#[test]
+	fn open_and_close_keep_discs_wider_than_the_radius() {
+		let region =
+			Region { outer: ArcPoly::circle(Vec2::ZERO, 50.0), ..default() };
+		let expected = region.area();
+		let tolerance = Tolerance::default();
+		for result in
+			[region.open(10.0, &tolerance), region.close(10.0, &tolerance)]
+		{
+			assert_eq!(result.len(), 1);
+			assert!((result[0].area() - expected).abs() < 1e-3 * expected);
+		}
+	}
+
+	#[test]
+	fn open_rounds_convex_corners_and_removes_slivers() {
+		let tolerance = Tolerance::default();
+		let plate = ArcPoly::rectangle(Rect::new(0.0, 0.0, 100.0, 50.0));
+		let sliver = ArcPoly::rectangle(Rect::new(100.0, 20.0, 140.0, 21.0));
+		let outer = boolean::union(&[plate], &[sliver], &tolerance);
+		assert_eq!(outer.len(), 1);
+		let region = Region { outer: outer[0].clone(), ..default() };
+		let opened = region.open(5.0, &tolerance);
+		assert_eq!(opened.len(), 1);
+		let expected = 5000.0 - (4.0 - std::f32::consts::PI) * 25.0;
+		assert!((opened[0].area() - expected).abs() < 1e-3 * expected);
+	}
+
+	#[test]
+	fn close_fills_pinholes() {
+		let region = Region {
+			outer: ArcPoly::circle(Vec2::ZERO, 50.0),
+			holes: vec![ArcPoly::circle(Vec2::new(10.0, 0.0), 1.0).reversed()],
+			..default()
+		};
+		let closed = region.close(5.0, &Tolerance::default());
+		assert_eq!(closed.len(), 1);
+		assert!(closed[0].holes.is_empty());
+		let expected = std::f32::consts::PI * 2500.0;
+		assert!((closed[0].area() - expected).abs() < 1e-3 * expected);
+	}
+
+	#[test]
+	fn from_loops_nests_by_containment() {
+		let circle =
+			|x: f32, radius: f32| ArcPoly::circle(Vec2::new(x, 0.0), radius);
+		let loops = vec![
+			circle(-30.0, 5.0),
+			circle(300.0, 10.0).reversed(),
+			circle(-30.0, 50.0),
+			circle(0.0, 100.0).reversed(),
+			circle(60.0, 10.0),
+			circle(-30.0, 20.0).reversed(),
+		];
+		let regions = Region::from_loops(loops);
+		assert_eq!(regions.len(), 2);
+		let (plate, apart) = (&regions[0], &regions[1]);
+		assert!(plate.outer.area() > 0.0 && apart.outer.area() > 0.0);
+		assert_eq!(plate.holes.len(), 2);
+		assert!(plate.holes.iter().all(|hole| hole.area() < 0.0));
+		assert_eq!(plate.islands.len(), 1);
+		let island = &plate.islands[0];
+		assert!(island.outer.area() > 0.0);
+		assert_eq!(island.holes.len(), 1);
+		assert!(island.holes[0].area() < 0.0);
+		assert!(island.islands.is_empty());
+		assert!(apart.holes.is_empty() && apart.islands.is_empty());
+	}
+
+	#[test]
+	fn dilation_by_radii_beyond_that_of_the_arcs() {
+		let tolerance = Tolerance::default();
+		let circle = ArcPoly::circle(Vec2::ZERO, 5.0);
+		for poly in [circle.clone(), circle.reversed()] {
+			for radius in [2.0, 5.0, 10.0, 40.0] {
+				let dilated = dilated(&[poly.clone()], radius, &tolerance);
+				assert_eq!(dilated.len(), 1, "radius {}", radius);
+				let expected = std::f32::consts::PI * (5.0f32 + radius).powi(2);
+				let area = dilated[0].area();
+				assert!((area - expected).abs() < 1e-3 * expected, "radius {}", radius);
+			}
+		}
+	}
+
+	#[test]
+	fn dilate_points_unites_overlapping_discs() {
+		let tolerance = Tolerance::default();
+		let points = [Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(50.0, 0.0)];
+		let regions = Region::dilate_points(&points, 2.0, &tolerance);
+		assert_eq!(regions.len(), 2);
+		let areas = regions.iter().map(Region::area).sorted_by(f32::total_cmp);
+		let disc = std::f32::consts::PI * 4.0;
+		// Two discs of radius 2 one apart overlap in a lens.
+		let lens = 8.0 * (0.25f32).acos() - 0.5 * 15.0f32.sqrt();
+		for (area, expected) in areas.zip([disc, 2.0 * disc - lens]) {
+			assert!((area - expected).abs() < 1e-3 * expected);
+		}
+		assert!(Region::dilate_points(&points, 0.0, &tolerance).is_empty());
+	}
+
+	#[test]
+	fn dilate_polyline_rounds_ends_and_outer_corners() {
+		let tolerance = Tolerance::default();
+		let points = [Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0)];
+		let regions = Region::dilate_polyline(&points, 1.0, &tolerance);
+		assert_eq!(regions.len(), 1);
+		assert!(regions[0].holes.is_empty());
+		// Two strips, the caps at either end and a quarter disc at the outer
+		// side of the corner, less the square where the strips overlap.
+		let expected = 40.0 + std::f32::consts::PI * 1.25 - 1.0;
+		assert!((regions[0].area() - expected).abs() < 1e-3 * expected);
+		let point =
+			Region::dilate_polyline(&[Vec2::ONE, Vec2::ONE], 1.0, &tolerance);
+		assert_eq!(point.len(), 1);
+		assert!((point[0].area() - std::f32::consts::PI).abs() < 1e-3);
+	}
+}
@@ -2,18 +2,56 @@ extern crate derive_more;
 use std::f32::consts::PI;
 
 use derive_more::Display;
+use itertools::Itertools;
 
 use bevy::{
-	ecs::component::Component, gizmos::gizmos::Gizmos, math::Vec2,
-	reflect::Reflect, render::color::Color,
+	ecs::component::Component,
+	gizmos::gizmos::Gizmos,
+	math::{DVec2, Rect, Vec2},
+	prelude::default,
+	reflect::Reflect,
+	render::color::Color,
 };
 
-use crate::math::{angle_counter_clockwise, bool_to_sign, Circle, FloatVec2};
+use crate::{
+	geom::arc_poly::SegIdx,
+	math::{
+		angle_counter_clockwise, bool_to_sign, circle_center_from_3_points,
+		circle_line_intersection, line_line_intersection, midpoint, mirror_point,
+		ApproxEq, Circle, CircleIntersection, FloatVec2, Tolerance, Transform2,
+		Xform2,
+	},
+};
 
-#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+#[derive(Clone, Copy, Debug, Display, Reflect, PartialEq)]
 pub enum Bend {
 	Inward,
 	Outward,
+	/// A line segment, whose `center` is unused. Not yet supported by
+	/// `ArcPoly::shrunk`.
+	Straight,
+}
+
+impl Bend {
+	pub fn flipped(&self) -> Bend {
+		match self {
+			Bend::Inward => Bend::Outward,
+			Bend::Outward => Bend::Inward,
+			Bend::Straight => Bend::Straight,
+		}
+	}
+}
+
+/// Machining and drawing attributes of a segment, carried along when the
+/// segment is split, shrunk or otherwise transformed, and given to machines
+/// by `toolpath::gcode`.
+#[derive(Clone, Copy, Default, Reflect, PartialEq)]
+pub struct ArcAttributes {
+	/// The layer by number rather than by name, so that segments stay
+	/// `Copy`. Naming layers is left to the application.
+	pub layer: u32,
+	pub feed: Option<f32>,
+	pub power: Option<f32>,
 }
 
 #[derive(Component, Copy, Reflect, Clone, Display)]
@@ -22,6 +60,56 @@ pub struct Segment {
 	pub initial: Vec2,
 	pub center: Vec2,
 	pub bend: Bend,
+	pub attributes: ArcAttributes,
+}
+
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum InvalidArc {
+	#[display(fmt = "non-finite coordinates")]
+	NonFinite,
+	#[display(fmt = "radius {} below threshold", _0)]
+	RadiusTooSmall(f32),
+	#[display(fmt = "span {} below threshold", _0)]
+	SpanTooSmall(f32),
+	#[display(fmt = "length {} below threshold", _0)]
+	TooShort(f32),
+}
+
+/// Where a segment ends. Arcs end where the next segment of their loop
+/// starts, except for the only segment of a loop, a full circle, which ends
+/// back at its own start after a full turn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SegEnd {
+	At(Vec2),
+	FullTurn,
+}
+
+impl SegEnd {
+	/// End of `segments[i]` in the loop they form.
+	pub fn in_loop(segments: &[Segment], i: usize) -> SegEnd {
+		match segments.len() {
+			1 => SegEnd::FullTurn,
+			n => SegEnd::At(segments[(i + 1) % n].initial),
+		}
+	}
+}
+
+/// Side of a directed curve on which a point lies, as seen travelling along
+/// it.
+#[derive(Clone, Copy, Debug, Display, Reflect, PartialEq)]
+pub enum Side {
+	Left,
+	Right,
+	/// Within the distance given to the query of the curve.
+	On,
+}
+
+/// A point where a segment meets another curve, which either crosses or, when
+/// `tangent`, touches it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+	pub point: Vec2,
+	pub tangent: bool,
 }
 
 #[derive(Display)]
@@ -29,26 +117,137 @@ pub struct Segment {
 pub struct Collision {
 	pub time_place: FloatVec2,
 	pub kind: CollisionType,
+	/// The segments whose circles meet in the event, as they are in the
+	/// naive shrink by the time of the event.
+	pub involved: Vec<SegmentSnapshot>,
+}
+
+/// Copy of a segment of a shrinking loop at some offset, with its end and
+/// radius at that offset.
+#[derive(Clone, Copy, Display)]
+#[display(fmt = "snapshot({}, {}, {})", idx, segment, radius)]
+pub struct SegmentSnapshot {
+	pub idx: SegIdx,
+	pub segment: Segment,
+	pub end: SegEnd,
+	pub radius: f32,
+}
+
+impl Collision {
+	/// Draws the involved segments and their full circles in `color`, and
+	/// the place of the event.
+	pub fn draw_gizmos(&self, gizmos: &mut Gizmos, color: &Color) {
+		for snapshot in self.involved.iter() {
+			draw_segment(&snapshot.segment, snapshot.end, gizmos, color);
+			if snapshot.segment.bend != Bend::Straight {
+				gizmos.circle_2d(
+					snapshot.segment.center,
+					snapshot.radius,
+					color.with_a(0.25),
+				);
+			}
+		}
+		gizmos.circle_2d(self.time_place.v, 6.0, *color);
+	}
 }
 
 #[derive(Display)]
 pub enum CollisionType {
 	#[display(fmt = "opposite({}, {})", first_idx, second_idx)]
-	Opposite { first_idx: usize, second_idx: usize },
+	Opposite { first_idx: SegIdx, second_idx: SegIdx },
 	#[display(fmt = "neighbors({})", idx)]
-	Neighbors { idx: usize },
+	Neighbors { idx: SegIdx },
 }
 
 impl Segment {
-	pub fn extreme(&self, next_initial: &Vec2) -> Vec2 {
-		0.5 * (self.initial + *next_initial)
-			+ 0.5
-				* self.outward(next_initial)
-				* bool_to_sign(self.bend == Bend::Outward)
+	/// Arc around `center` from `start`, counter-clockwise if `ccw`. It ends
+	/// wherever the next segment starts.
+	pub fn from_center_start(center: Vec2, start: Vec2, ccw: bool) -> Segment {
+		Segment {
+			initial: start,
+			center,
+			bend: if ccw { Bend::Outward } else { Bend::Inward },
+			attributes: default(),
+		}
+	}
+
+	pub fn straight(start: Vec2, end: Vec2) -> Segment {
+		Segment {
+			initial: start,
+			center: 0.5 * (start + end),
+			bend: Bend::Straight,
+			attributes: default(),
+		}
 	}
 
-	pub fn outward(&self, next_initial: &Vec2) -> Vec2 {
-		(*next_initial - self.initial).rotate(Vec2::NEG_Y)
+	/// Arc leaving `start` along `tangent` and ending at `end`, or a straight
+	/// segment if `end` lies straight ahead.
+	pub fn from_start_tangent_end(
+		start: Vec2,
+		tangent: Vec2,
+		end: Vec2,
+	) -> Segment {
+		let normal = tangent.perp().normalize();
+		let d = end - start;
+		// Signed radius of the circle through both points centered on the normal.
+		let r = d.length_squared() / (2.0 * normal.dot(d));
+		if !r.is_finite() {
+			return Segment::straight(start, end);
+		}
+		Segment::from_center_start(start + r * normal, start, r > 0.0)
+	}
+
+	/// Arc from `start` through `mid` to `end`, or a straight segment if
+	/// they are collinear.
+	pub fn from_three_points(start: Vec2, mid: Vec2, end: Vec2) -> Segment {
+		let turn = (mid - start).perp_dot(end - mid);
+		if turn == 0.0 {
+			return Segment::straight(start, end);
+		}
+		let center = circle_center_from_3_points(&start, &mid, &end);
+		Segment::from_center_start(center, start, turn > 0.0)
+	}
+
+	/// The first of the checks against `tolerance` that fails, if any.
+	pub fn validate(
+		&self,
+		end: SegEnd,
+		tolerance: &Tolerance,
+	) -> Result<(), InvalidArc> {
+		if !(self.initial.is_finite()
+			&& self.end_point(end).is_finite()
+			&& (self.bend == Bend::Straight || self.center.is_finite()))
+		{
+			return Err(InvalidArc::NonFinite);
+		}
+		if self.bend == Bend::Straight {
+			let length = self.length(end);
+			return match length < tolerance.min_length {
+				true => Err(InvalidArc::TooShort(length)),
+				false => Ok(()),
+			};
+		}
+		let radius = self.radius();
+		if radius < tolerance.min_radius {
+			return Err(InvalidArc::RadiusTooSmall(radius));
+		}
+		let span = self.span(end);
+		if span < tolerance.min_span {
+			return Err(InvalidArc::SpanTooSmall(span));
+		}
+		Ok(())
+	}
+
+	pub fn extreme(&self, end: SegEnd) -> Vec2 {
+		if self.bend == Bend::Straight {
+			return 0.5 * (self.initial + self.end_point(end));
+		}
+		0.5 * (self.initial + self.end_point(end))
+			+ 0.5 * self.outward(end) * bool_to_sign(self.bend == Bend::Outward)
+	}
+
+	pub fn outward(&self, end: SegEnd) -> Vec2 {
+		(self.end_point(end) - self.initial).rotate(Vec2::NEG_Y)
 	}
 
 	pub fn ca(&self) -> Vec2 {
@@ -63,8 +262,29 @@ impl Segment {
 		self.ca().length()
 	}
 
-	pub fn angle(&self, next_initial: &Vec2) -> f32 {
-		angle_gen(&self.ca(), &self.cb(next_initial), self.bend)
+	/// Angle travelled from `initial` to `point` on the segment's circle, in
+	/// `0..2π`.
+	pub fn angle(&self, point: &Vec2) -> f32 {
+		angle_gen(&self.ca(), &self.cb(point), self.bend)
+	}
+
+	/// Angle travelled from `initial` to `end`, a full turn for
+	/// `SegEnd::FullTurn` and zero for an arc ending where it starts.
+	/// Zero for straight segments.
+	pub fn span(&self, end: SegEnd) -> f32 {
+		match (self.bend, end) {
+			(Bend::Straight, _) => 0.0,
+			(_, SegEnd::FullTurn) => 2.0 * PI,
+			(_, SegEnd::At(point)) => self.angle(&point),
+		}
+	}
+
+	/// Point where the segment ends.
+	pub fn end_point(&self, end: SegEnd) -> Vec2 {
+		match end {
+			SegEnd::At(point) => point,
+			SegEnd::FullTurn => self.initial,
+		}
 	}
 
 	pub fn angle_a(&self) -> f32 {
@@ -81,36 +301,608 @@ impl Segment {
 		FloatVec2 { v: self.center, f: self.radius() }
 	}
 
+	/// Unit direction of travel at `point` on the segment's circle or line.
+	pub fn tangent_at(&self, end: SegEnd, point: &Vec2) -> Vec2 {
+		if self.bend == Bend::Straight {
+			return (self.end_point(end) - self.initial).normalize();
+		}
+		(*point - self.center).perp().normalize()
+			* bool_to_sign(self.bend == Bend::Outward)
+	}
+
+	/// Unit normal at `point` to the right of the direction of travel. With
+	/// the sign convention of `sweep`, it points away from `center` on
+	/// counter-clockwise arcs, whose sweep is positive, and towards it on
+	/// clockwise ones.
+	pub fn normal_at(&self, end: SegEnd, point: &Vec2) -> Vec2 {
+		-self.tangent_at(end, point).perp()
+	}
+
+	/// Side of the segment's circle or line on which `point` lies, left being
+	/// inside the circle of counter-clockwise arcs and outside that of
+	/// clockwise ones. Points within `distance` of the circle or line are
+	/// `Side::On`.
+	pub fn side_of(&self, end: SegEnd, point: &Vec2, distance: f32) -> Side {
+		let offset = match self.bend {
+			Bend::Straight => {
+				let chord = (self.end_point(end) - self.initial).normalize();
+				chord.perp_dot(*point - self.initial)
+			}
+			_ => {
+				(self.radius() - (*point - self.center).length())
+					* bool_to_sign(self.bend == Bend::Outward)
+			}
+		};
+		if offset.abs() <= distance {
+			Side::On
+		} else if offset > 0.0 {
+			Side::Left
+		} else {
+			Side::Right
+		}
+	}
+
+	/// Angle travelled around `center`, positive when counter-clockwise.
+	pub fn sweep(&self, end: SegEnd) -> f32 {
+		self.span(end) * bool_to_sign(self.bend == Bend::Outward)
+	}
+
+	pub fn length(&self, end: SegEnd) -> f32 {
+		if self.bend == Bend::Straight {
+			return (self.end_point(end) - self.initial).length();
+		}
+		self.radius() * self.span(end)
+	}
+
+	/// Signed area between the chord and the arc, positive when the arc is
+	/// travelled counter-clockwise.
+	pub fn lens_area(&self, end: SegEnd) -> f32 {
+		let span = self.span(end);
+		0.5
+			* self.radius().powi(2)
+			* (span - span.sin())
+			* bool_to_sign(self.bend == Bend::Outward)
+	}
+
+	/// Average of the points along the segment, weighted by arc length. For
+	/// an arc it lies on the bisector of the chord, `r * chord / length` away
+	/// from the center, so at the center itself for a full circle. Segments
+	/// without length have `initial` as their centroid.
+	pub fn centroid(&self, end: SegEnd) -> Vec2 {
+		let chord = self.end_point(end) - self.initial;
+		match (self.bend, self.length(end)) {
+			(_, 0.0) => self.initial,
+			(Bend::Straight, _) => self.initial + 0.5 * chord,
+			(_, length) => {
+				self.center
+					- bool_to_sign(self.bend == Bend::Outward) * self.radius() / length
+						* chord.perp()
+			}
+		}
+	}
+
+	/// Fraction of the segment travelled at `point`, which is assumed to lie
+	/// on the segment's circle or line. Within `0..=1` exactly for points on
+	/// the segment.
+	pub fn fraction_at(&self, end: SegEnd, point: &Vec2) -> f32 {
+		if self.bend == Bend::Straight {
+			let chord = self.end_point(end) - self.initial;
+			(*point - self.initial).dot(chord) / chord.length_squared()
+		} else {
+			self.angle(point) / self.span(end)
+		}
+	}
+
+	/// Point of the arc closest to `point`.
+	pub fn closest_point(&self, end: SegEnd, point: &Vec2) -> Vec2 {
+		if self.bend == Bend::Straight {
+			let t = self.fraction_at(end, point);
+			return self.point_at(end, t.clamp(0.0, 1.0));
+		}
+		let projected = (*point - self.center)
+			.try_normalize()
+			.map_or(self.initial, |dir| self.center + self.radius() * dir);
+		if self.angle(&projected) <= self.span(end) {
+			projected
+		} else if (self.initial - *point).length()
+			< (self.end_point(end) - *point).length()
+		{
+			self.initial
+		} else {
+			self.end_point(end)
+		}
+	}
+
+	/// Point of the arc farthest from `point`.
+	pub fn farthest_point(&self, end: SegEnd, point: &Vec2) -> Vec2 {
+		let opposite = (self.center - *point)
+			.try_normalize()
+			.filter(|_| self.bend != Bend::Straight)
+			.map(|dir| self.center + self.radius() * dir)
+			.filter(|p| self.angle(p) <= self.span(end));
+		opposite
+			.into_iter()
+			.chain([self.initial, self.end_point(end)])
+			.max_by(|p, q| (*p - *point).length().total_cmp(&(*q - *point).length()))
+			.unwrap()
+	}
+
+	/// `closest_point` together with its fraction of the sweep, as taken by
+	/// `point_at`.
+	pub fn project(&self, end: SegEnd, point: &Vec2) -> (f32, Vec2) {
+		let closest = self.closest_point(end, point);
+		let t = if closest == self.end_point(end) {
+			1.0
+		} else {
+			self.fraction_at(end, &closest)
+		};
+		(t, closest)
+	}
+
+	pub fn distance_to_point(&self, end: SegEnd, point: &Vec2) -> f32 {
+		(self.closest_point(end, point) - *point).length()
+	}
+
+	/// Point at fraction `t` of the sweep, `t = 0` being `initial`.
+	pub fn point_at(&self, end: SegEnd, t: f32) -> Vec2 {
+		if self.bend == Bend::Straight {
+			return self.initial.lerp(self.end_point(end), t);
+		}
+		self.center + Vec2::from_angle(self.sweep(end) * t).rotate(self.ca())
+	}
+
+	/// `n + 1` points at equal steps from `initial` to `end`, both
+	/// included.
+	pub fn sample_n<'a>(
+		&'a self,
+		end: SegEnd,
+		n: usize,
+	) -> impl DoubleEndedIterator<Item = Vec2> + 'a {
+		(0..=n).map(move |k| {
+			if k == n {
+				self.end_point(end)
+			} else {
+				self.point_at(end, k as f32 / n as f32)
+			}
+		})
+	}
+
+	/// `sample_n` with the fewest steps whose chords deviate at most
+	/// `tolerance` from the arc. Tolerances below the rounding of the radius,
+	/// zero and negative ones included, are raised to it, which bounds the
+	/// number of points.
+	pub fn sample<'a>(
+		&'a self,
+		end: SegEnd,
+		tolerance: f32,
+	) -> impl DoubleEndedIterator<Item = Vec2> + 'a {
+		let n = match self.bend {
+			Bend::Straight => 1,
+			_ => {
+				let radius = self.radius();
+				let tolerance = tolerance.max(f32::EPSILON * radius);
+				let max_step = 2.0 * (1.0 - tolerance / radius).max(-1.0).acos();
+				(self.span(end) / max_step).ceil().max(1.0) as usize
+			}
+		};
+		self.sample_n(end, n)
+	}
+
+	/// The same arc travelled from `end` back to `initial`, which
+	/// negates the sweep and tangents while keeping the circle.
+	pub fn reversed(&self, end: SegEnd) -> Segment {
+		Segment { initial: self.end_point(end), bend: self.bend.flipped(), ..*self }
+	}
+
+	/// Point at arc length `s` from `initial`, which is `initial` itself for
+	/// segments without length.
+	pub fn point_at_length(&self, end: SegEnd, s: f32) -> Vec2 {
+		match self.length(end) {
+			0.0 => self.initial,
+			length => self.point_at(end, s / length),
+		}
+	}
+
+	pub fn tangent_at_length(&self, end: SegEnd, s: f32) -> Vec2 {
+		self.tangent_at(end, &self.point_at_length(end, s))
+	}
+
+	/// The arc cut at fraction `t` of the sweep into two consecutive arcs,
+	/// the second ending at `end`.
+	pub fn split_at(&self, end: SegEnd, t: f32) -> [Segment; 2] {
+		[*self, Segment { initial: self.point_at(end, t), ..*self }]
+	}
+
+	/// The arc cut into `n` consecutive arcs of equal sweep.
+	pub fn subdivide(&self, end: SegEnd, n: usize) -> Vec<Segment> {
+		(0..n)
+			.map(|i| Segment {
+				initial: self.point_at(end, i as f32 / n as f32),
+				..*self
+			})
+			.collect()
+	}
+
+	/// Whether `point` lies between the chord and the arc. The side of the
+	/// chord is taken from the bend rather than from a point on the arc, which
+	/// on short arcs may be closer to the chord than rounding allows to tell.
+	/// Points on the chord count as right of it. The side is taken in `f64`,
+	/// which rounds far less than the `f32` coordinates but is not exact. The
+	/// lens of a full circle is its disc, that of an arc ending where it
+	/// starts is empty.
+	pub fn in_lens(&self, end: SegEnd, point: &Vec2) -> bool {
+		if self.bend == Bend::Straight
+			|| (*point - self.center).length() >= self.radius()
+		{
+			return false;
+		}
+		// Counter-clockwise arcs lie to the right of their chord.
+		let initial = self.initial.as_dvec2();
+		let chord = match end {
+			SegEnd::FullTurn => return true,
+			SegEnd::At(next_initial) => next_initial.as_dvec2() - initial,
+		};
+		chord != DVec2::ZERO
+			&& (chord.perp_dot(point.as_dvec2() - initial) > 0.0)
+				== (self.bend == Bend::Inward)
+	}
+
+	/// Smallest axis aligned rectangle containing the arc.
+	pub fn bounds(&self, end: SegEnd) -> Rect {
+		let extremes = match self.bend {
+			Bend::Straight => vec![],
+			_ => self.on_arc(
+				end,
+				[Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y]
+					.into_iter()
+					.map(|dir| self.center + self.radius() * dir),
+			),
+		};
+		extremes
+			.into_iter()
+			.fold(Rect::from_corners(self.initial, self.end_point(end)), |r, p| {
+				r.union_point(p)
+			})
+	}
+
+	/// Crossings and tangencies of the arc with `circle`, in order of travel,
+	/// where curves within `tol` of touching are considered tangent. A circle
+	/// coinciding with the arc's own circle yields no points.
+	pub fn intersect_circle(
+		&self,
+		end: SegEnd,
+		circle: &Circle,
+		tol: f32,
+	) -> Vec<Contact> {
+		let (points, tangent) = match self.bend {
+			Bend::Straight => {
+				let chord = self.end_point(end) - self.initial;
+				circle_line_contacts(circle, &self.initial, &chord, tol)
+			}
+			_ => {
+				let intersection = self.circle().intersect(circle, tol);
+				let tangent = matches!(intersection, CircleIntersection::Tangent(_));
+				(intersection.points(), tangent)
+			}
+		};
+		self
+			.on_arc(end, points.into_iter())
+			.into_iter()
+			.map(|point| Contact { point, tangent })
+			.collect()
+	}
+
+	/// Points where the arc meets the line through `origin` along `dir`, in
+	/// order of travel.
+	pub fn intersect_line(
+		&self,
+		end: SegEnd,
+		origin: &Vec2,
+		dir: &Vec2,
+	) -> Vec<Vec2> {
+		self.on_arc(
+			end,
+			self.line_params(end, origin, dir).map(|t| *origin + t * *dir),
+		)
+	}
+
+	/// Signed number of times the segment crosses the ray from `origin` along
+	/// the x axis, upwards counted positive. Arcs are cut at their top and
+	/// bottom into pieces monotone in height, each of which covers the
+	/// heights from its lower end up to but excluding its upper end, so that
+	/// a ray through a joint of two segments is counted once.
+	pub fn ray_crossings(&self, end: SegEnd, origin: &Vec2) -> i32 {
+		let span = self.span(end);
+		let mut stops = vec![(0.0, self.initial)];
+		if self.bend != Bend::Straight {
+			let radius = self.radius();
+			stops.extend(
+				[Vec2::Y, Vec2::NEG_Y]
+					.map(|dir| self.center + radius * dir)
+					.map(|p| (self.angle(&p), p))
+					.into_iter()
+					.filter(|(angle, _)| *angle > 0.0 && *angle < span)
+					.sorted_by(|a, b| a.0.total_cmp(&b.0)),
+			);
+		}
+		stops.push((span, self.end_point(end)));
+		stops
+			.into_iter()
+			.tuple_windows()
+			.map(|((from_angle, a), (to_angle, b))| {
+				let (low, high) = if a.y < b.y { (a, b) } else { (b, a) };
+				if !(low.y <= origin.y && origin.y < high.y) {
+					return 0;
+				}
+				let x = match self.bend {
+					Bend::Straight => {
+						low.x + (origin.y - low.y) / (high.y - low.y) * (high.x - low.x)
+					}
+					_ => {
+						let mid = self.point_at(end, 0.5 * (from_angle + to_angle) / span);
+						let dy = origin.y - self.center.y;
+						let half_chord = (self.radius().powi(2) - dy * dy).max(0.0).sqrt();
+						self.center.x + (mid.x - self.center.x).signum() * half_chord
+					}
+				};
+				match (x > origin.x, b.y > a.y) {
+					(false, _) => 0,
+					(true, true) => 1,
+					(true, false) => -1,
+				}
+			})
+			.sum()
+	}
+
+	/// Points where the arc meets the line segment from `a` to `b`, in order
+	/// of travel.
+	pub fn intersect_line_segment(
+		&self,
+		end: SegEnd,
+		a: &Vec2,
+		b: &Vec2,
+	) -> Vec<Vec2> {
+		let dir = *b - *a;
+		self.on_arc(
+			end,
+			self
+				.line_params(end, a, &dir)
+				.filter(|t| (0.0..=1.0).contains(t))
+				.map(|t| *a + t * dir),
+		)
+	}
+
+	/// Points where the segment meets `other`, which ends at `other_end`, in
+	/// order of travel. Overlapping segments yield no points.
+	pub fn intersect_segment(
+		&self,
+		end: SegEnd,
+		other: &Segment,
+		other_end: SegEnd,
+	) -> Vec<Vec2> {
+		self
+			.contacts_with_segment(end, other, other_end, 0.0)
+			.into_iter()
+			.map(|contact| contact.point)
+			.collect()
+	}
+
+	/// `intersect_segment` with the curves within `tol` of touching taken as
+	/// tangent. The two crossings of near-tangent curves, which would
+	/// otherwise cut both segments twice at nearly the same place, are merged
+	/// into a single tangent contact when closer than `tol`.
+	pub fn contacts_with_segment(
+		&self,
+		end: SegEnd,
+		other: &Segment,
+		other_end: SegEnd,
+		tol: f32,
+	) -> Vec<Contact> {
+		let (mut points, mut tangent) = match (self.bend, other.bend) {
+			(Bend::Straight, Bend::Straight) => {
+				let dir = self.end_point(end) - self.initial;
+				let other_dir = other.end_point(other_end) - other.initial;
+				let points = line_line_intersection(
+					&self.initial,
+					&dir,
+					&other.initial,
+					&other_dir,
+				)
+				.map(|(t, _)| self.initial + t * dir);
+				(points.into_iter().collect_vec(), false)
+			}
+			(_, Bend::Straight) => {
+				let dir = other.end_point(other_end) - other.initial;
+				circle_line_contacts(&self.circle(), &other.initial, &dir, tol)
+			}
+			(Bend::Straight, _) => {
+				let dir = self.end_point(end) - self.initial;
+				circle_line_contacts(&other.circle(), &self.initial, &dir, tol)
+			}
+			_ => {
+				let intersection = self.circle().intersect(&other.circle(), tol);
+				let tangent = matches!(intersection, CircleIntersection::Tangent(_));
+				(intersection.points(), tangent)
+			}
+		};
+		if let [p, q] = points[..] {
+			if (p - q).length() <= tol {
+				points = vec![midpoint(&p, &q)];
+				tangent = true;
+			}
+		}
+		let points = other.on_arc(other_end, points.into_iter());
+		self
+			.on_arc(end, points.into_iter())
+			.into_iter()
+			.map(|point| Contact { point, tangent })
+			.collect()
+	}
+
+	/// Parameters at which the line `origin + t * dir` meets the segment's
+	/// circle or line.
+	fn line_params(
+		&self,
+		end: SegEnd,
+		origin: &Vec2,
+		dir: &Vec2,
+	) -> impl Iterator<Item = f32> {
+		match self.bend {
+			Bend::Straight => {
+				let chord = self.end_point(end) - self.initial;
+				line_line_intersection(origin, dir, &self.initial, &chord)
+					.map(|(t, _)| t)
+					.into_iter()
+					.collect_vec()
+			}
+			_ => circle_line_intersection(&self.circle(), origin, dir),
+		}
+		.into_iter()
+	}
+
+	/// Those of the points on the segment's circle or line which lie on the
+	/// segment, sorted in order of travel.
+	fn on_arc(
+		&self,
+		end: SegEnd,
+		points: impl Iterator<Item = Vec2>,
+	) -> Vec<Vec2> {
+		points
+			.map(|p| (self.fraction_at(end, &p), p))
+			.filter(|(t, _)| (0.0..=1.0).contains(t))
+			.sorted_by(|(s, _), (t, _)| s.total_cmp(t))
+			.map(|(_, p)| p)
+			.collect()
+	}
+
 	pub fn circle_neg_r(&self) -> Circle {
 		FloatVec2 {
 			v: self.center,
 			f: self.radius() * bool_to_sign(self.bend == Bend::Inward),
 		}
 	}
+
+	/// The segment with its initial and center points transformed by `xform`,
+	/// so that the radius scales by `scale.abs()`.
+	pub fn transformed(&self, xform: &Xform2) -> Segment {
+		Segment {
+			initial: xform.apply(&self.initial),
+			center: xform.apply(&self.center),
+			..*self
+		}
+	}
+}
+
+impl ApproxEq for Segment {
+	/// The centers of straight segments are not compared as they are unused.
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		self.bend == other.bend
+			&& self.initial.approx_eq(&other.initial, epsilon)
+			&& (self.bend == Bend::Straight
+				|| self.center.approx_eq(&other.center, epsilon))
+	}
+}
+
+impl Transform2 for Segment {
+	fn apply_xform(&mut self, xform: &Xform2) {
+		*self = self.transformed(xform);
+	}
+
+	fn mirror(&mut self, origin: Vec2, dir: Vec2) {
+		self.initial = mirror_point(&self.initial, origin, dir);
+		self.center = mirror_point(&self.center, origin, dir);
+		self.bend = self.bend.flipped();
+	}
+}
+
+/// Points where `circle` meets the line through `origin` along `dir`, and
+/// whether they are a single tangent point, as the foot of the center is
+/// taken to be when the line is within `tol` of touching the circle.
+fn circle_line_contacts(
+	circle: &Circle,
+	origin: &Vec2,
+	dir: &Vec2,
+	tol: f32,
+) -> (Vec<Vec2>, bool) {
+	let t = (circle.v - *origin).dot(*dir) / dir.length_squared();
+	let foot = *origin + t * *dir;
+	if ((foot - circle.v).length() - circle.f).abs() <= tol {
+		return (vec![foot], true);
+	}
+	let ts = circle_line_intersection(circle, origin, dir);
+	let tangent = ts.len() == 1;
+	(ts.into_iter().map(|t| *origin + t * *dir).collect(), tangent)
 }
 
 pub fn angle_gen(ca: &Vec2, cb: &Vec2, bend: Bend) -> f32 {
-	if bend == Bend::Outward {
-		angle_counter_clockwise(ca, cb)
-	} else {
-		angle_counter_clockwise(cb, ca)
+	match bend {
+		Bend::Outward => angle_counter_clockwise(ca, cb),
+		Bend::Inward => angle_counter_clockwise(cb, ca),
+		Bend::Straight => 0.0,
 	}
 }
 
 pub fn draw_segment(
 	a: &Segment,
-	b_initial: &Vec2,
+	end: SegEnd,
 	gizmos: &mut Gizmos,
 	color: &Color,
 ) {
 	gizmos.circle_2d(a.initial, 2.0, Color::BLACK);
-	gizmos.circle_2d(*b_initial, 4.0, Color::GRAY);
+	gizmos.circle_2d(a.end_point(end), 4.0, Color::GRAY);
+	if a.bend == Bend::Straight {
+		gizmos.line_2d(a.initial, a.end_point(end), *color);
+		return;
+	}
 	gizmos.arc_2d(
 		Vec2::from_array(a.center.into()),
-		a.outward(b_initial).angle_between(Vec2::Y)
+		a.outward(end).angle_between(Vec2::Y)
 			+ (a.bend == Bend::Inward).then_some(PI).unwrap_or(0.0),
-		a.angle(b_initial),
+		a.span(end),
 		a.radius(),
 		*color,
 	);
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn point_at_length_of_segments_without_length_is_their_start() {
+		let start = Vec2::new(1.0, 2.0);
+		let straight = Segment::straight(start, start);
+		let arc = Segment::from_center_start(Vec2::ZERO, start, true);
+		for segment in [straight, arc] {
+			assert_eq!(segment.point_at_length(SegEnd::At(start), 0.0), start);
+			assert_eq!(segment.point_at_length(SegEnd::At(start), 1.0), start);
+		}
+		let opposite =
+			arc.point_at_length(SegEnd::FullTurn, 0.5 * arc.length(SegEnd::FullTurn));
+		assert!(opposite.approx_eq(&-start, 1e-5));
+	}
+
+	#[test]
+	fn reversed_segments_retrace_the_same_points() {
+		let (start, end) = (Vec2::new(3.0, 0.0), Vec2::new(0.0, 3.0));
+		for segment in [
+			Segment::from_center_start(Vec2::ZERO, start, true),
+			Segment::from_center_start(Vec2::ZERO, start, false),
+			Segment::straight(start, end),
+		] {
+			let reversed = segment.reversed(SegEnd::At(end));
+			let back = SegEnd::At(start);
+			assert_eq!(reversed.initial, end);
+			assert_eq!(reversed.sweep(back), -segment.sweep(SegEnd::At(end)));
+			assert_eq!(reversed.length(back), segment.length(SegEnd::At(end)));
+			for t in [0.0, 0.25, 0.5, 1.0] {
+				let point = segment.point_at(SegEnd::At(end), t);
+				assert!(reversed.point_at(back, 1.0 - t).approx_eq(&point, 1e-5));
+				assert!(reversed
+					.tangent_at(back, &point)
+					.approx_eq(&-segment.tangent_at(SegEnd::At(end), &point), 1e-5));
+			}
+			assert!(reversed.reversed(back).approx_eq(&segment, 1e-6));
+		}
+	}
+}
@@ -1,8 +1,31 @@
+pub mod document;
+
+pub mod fill;
+
+pub mod format;
+
 pub mod geom {
 	pub mod arc_poly;
+	pub mod boolean;
+	pub mod grid;
+	pub mod index;
+	pub mod path;
+	pub mod region;
 	pub mod segment;
 }
 
 pub mod math;
 
+pub mod offset;
+
+pub mod palette;
+
+pub mod plugin;
+
+pub mod raster;
+
+pub mod scenarios;
+
+pub mod toolpath;
+
 pub mod util;
@@ -1,45 +1,548 @@
-use std::borrow::BorrowMut;
+use std::time::{Duration, Instant};
 
 use bevy::{
 	app::{App, Startup, Update},
 	core_pipeline::core_2d::Camera2dBundle,
-	ecs::system::{Commands, Query},
-	gizmos::gizmos::Gizmos,
+	ecs::system::Commands,
 	prelude::*,
+	render::camera::CameraUpdateSystem,
+	tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
 	DefaultPlugins,
 };
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
-use rarc::geom::arc_poly::{ArcPoly, ArcPolyGenInput};
+use rarc::{
+	document::{Document, ShapeId},
+	geom::{
+		arc_poly::{ArcPoly, ArcPolyGenInput},
+		segment::Collision,
+	},
+	math::{FloatBackend, Tolerance},
+	offset::{approximate_traced, TracedOffset},
+	palette::Palette,
+	plugin::{PendingShrink, RarcPlugin, RarcSystems, Shrunk},
+	scenarios,
+	util::{fit_camera_to, Cancellation, Warnings},
+};
+
+const INPUT_LAYER: usize = 0;
+const OFFSET_LAYER: usize = 1;
+const CHECK_TOLERANCE: f32 = 0.1;
+/// World units kept free around the input when framing it.
+const FRAME_MARGIN: f32 = 20.0;
+const PRESETS_PATH: &str = "presets.txt";
+/// Step of the radius slider position.
+const RADIUS_STEP: f32 = 0.02;
+const SHRINK_STEP: f32 = 2.5;
+
+/// Generator inputs loaded with the number keys, one per line of
+/// `PRESETS_PATH` in the field order of `ArcPolyGenInput` or given by the
+/// name of one of `scenarios::SCENARIOS`.
+#[derive(Resource, Default)]
+struct Presets(Vec<String>);
+
+/// Draws the polyline offset traced by `check_offset` next to the exact one,
+/// marking its points farther than `highlight_distance` from the exact
+/// offset.
+#[derive(Resource, Reflect)]
+struct Comparison {
+	enabled: bool,
+	/// Shift of the polyline offset, zero to overlay the two.
+	shift: Vec2,
+	highlight_distance: f32,
+}
+
+impl Default for Comparison {
+	fn default() -> Self {
+		Comparison {
+			enabled: false,
+			shift: Vec2::new(600.0, 0.0),
+			highlight_distance: 5.0 * CHECK_TOLERANCE,
+		}
+	}
+}
+
+#[derive(Resource, Default)]
+struct TracedLoops(Vec<Vec<Vec2>>);
+
+/// Polyline offset of an input shape being traced off-thread by
+/// `check_offset`, collected by `show_traces`.
+#[derive(Component)]
+struct PendingTrace {
+	shape: ShapeId,
+	task: Task<TracedOffset>,
+}
+
+/// Draws up to `count` offsets `step` apart in distinct colors.
+#[derive(Resource, Reflect)]
+struct Concentric {
+	enabled: bool,
+	step: f32,
+	count: usize,
+}
+
+impl Default for Concentric {
+	fn default() -> Self {
+		Concentric { enabled: false, step: 15.0, count: 8 }
+	}
+}
+
+#[derive(Resource, Default)]
+struct ConcentricOffsets(Vec<Vec<ArcPoly>>);
+
+/// Draws the first `count` collisions the input runs into while shrinking,
+/// with the involved segments as they are at the time of each event.
+#[derive(Resource, Reflect)]
+struct CollisionEvents {
+	enabled: bool,
+	count: usize,
+}
+
+impl Default for CollisionEvents {
+	fn default() -> Self {
+		CollisionEvents { enabled: false, count: 3 }
+	}
+}
+
+#[derive(Resource, Default)]
+struct UpcomingCollisions(Vec<Collision>);
+
+/// Durations of the latest run of each stage, shown in the corner by
+/// `update_timing_hud`. The offsets are timed from spawning their tasks to
+/// receiving the last result.
+#[derive(Resource, Default)]
+struct StageTimings {
+	generate: Duration,
+	shrink: Duration,
+	trace: Duration,
+	shrink_started: Option<Instant>,
+	trace_started: Option<Instant>,
+}
+
+#[derive(Component)]
+struct TimingHud;
 
 fn main() {
 	App::new()
 		.init_resource::<ArcPolyGenInput>()
+		.insert_resource(load_presets())
+		.init_resource::<Comparison>()
+		.init_resource::<TracedLoops>()
+		.init_resource::<StageTimings>()
+		.init_resource::<Concentric>()
+		.init_resource::<ConcentricOffsets>()
+		.init_resource::<CollisionEvents>()
+		.init_resource::<UpcomingCollisions>()
 		.add_plugins(DefaultPlugins)
+		.add_plugins(RarcPlugin)
 		.add_plugins(ResourceInspectorPlugin::<ArcPolyGenInput>::new())
+		.add_plugins(ResourceInspectorPlugin::<Warnings>::new())
+		.add_plugins(ResourceInspectorPlugin::<Comparison>::new())
+		.add_plugins(ResourceInspectorPlugin::<Concentric>::new())
+		.add_plugins(ResourceInspectorPlugin::<CollisionEvents>::new())
 		.add_systems(Startup, setup)
-		.add_systems(Update, update)
+		.add_systems(
+			Update,
+			(hotkeys, generate).chain().in_set(RarcSystems::Ingest),
+		)
+		.add_systems(
+			Update,
+			(
+				offset,
+				check_offset.before(concentric_offsets),
+				concentric_offsets,
+				upcoming_collisions,
+			)
+				.in_set(RarcSystems::Offset),
+		)
+		.add_systems(Update, (show_offset, show_traces).after(RarcSystems::Offset))
+		.add_systems(PostUpdate, frame_input.after(CameraUpdateSystem))
+		.add_systems(
+			Update,
+			(draw_comparison, draw_concentric, draw_collisions, update_timing_hud)
+				.in_set(RarcSystems::Draw),
+		)
 		.run();
 }
 
-fn setup(mut commands: Commands, gen_input: ResMut<ArcPolyGenInput>) {
+fn setup(mut commands: Commands, mut document: ResMut<Document>) {
 	commands.spawn(Camera2dBundle::default());
-	commands.spawn(ArcPoly::from_gen_input(&gen_input));
+	commands.spawn((
+		TextBundle::from_section("", TextStyle { font_size: 16.0, ..default() })
+			.with_style(Style {
+				position_type: PositionType::Absolute,
+				bottom: Val::Px(8.0),
+				left: Val::Px(8.0),
+				..default()
+			}),
+		TimingHud,
+	));
+	let background = ClearColor::default().0;
+	document.add_layer("input", Palette::OkabeIto.color_on(0, background));
+	document.add_layer("offset", Palette::OkabeIto.color_on(1, background));
 }
 
-fn update(
-	mut gizmos: Gizmos,
-	gen_input: ResMut<ArcPolyGenInput>,
-	mut arc_poly_query: Query<&mut ArcPoly>,
+fn load_presets() -> Presets {
+	let text = std::fs::read_to_string(PRESETS_PATH).unwrap_or_default();
+	Presets(
+		text
+			.lines()
+			.filter(|line| parse_preset(line).is_some())
+			.map(String::from)
+			.collect(),
+	)
+}
+
+fn parse_preset(line: &str) -> Option<ArcPolyGenInput> {
+	if let Some(scenario) = scenarios::find(line.trim()) {
+		return Some(scenario.gen_input());
+	}
+	let mut fields = line.split_whitespace();
+	let mut next = || fields.next();
+	let random_seed = next()?.parse().ok()?;
+	let n = next()?.parse().ok()?;
+	let r = next()?.parse().ok()?;
+	let mut gen_input = ArcPolyGenInput {
+		random_seed,
+		n,
+		r: ArcPolyGenInput::default().r,
+		offset_noise: next()?.parse().ok()?,
+		bend_max: next()?.parse().ok()?,
+		bend_min: next()?.parse().ok()?,
+		shrink: next()?.parse().ok()?,
+	};
+	gen_input.r.set_value(r);
+	Some(gen_input)
+}
+
+fn format_preset(gen_input: &ArcPolyGenInput) -> String {
+	format!(
+		"{} {} {} {} {} {} {}",
+		gen_input.random_seed,
+		gen_input.n,
+		gen_input.r.value(),
+		gen_input.offset_noise,
+		gen_input.bend_max,
+		gen_input.bend_min,
+		gen_input.shrink
+	)
+}
+
+/// Arrow keys nudge the seed and radius, page up and down the shrink amount.
+/// Keys 1 to 9 load the corresponding preset and S appends the current
+/// input to the presets file.
+fn hotkeys(
+	keys: Res<ButtonInput<KeyCode>>,
+	mut gen_input: ResMut<ArcPolyGenInput>,
+	mut presets: ResMut<Presets>,
+) {
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		gen_input.random_seed = gen_input.random_seed.wrapping_add(1);
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		gen_input.random_seed = gen_input.random_seed.wrapping_sub(1);
+	}
+	if keys.just_pressed(KeyCode::ArrowRight) {
+		gen_input.r.position = (gen_input.r.position + RADIUS_STEP).min(1.0);
+	}
+	if keys.just_pressed(KeyCode::ArrowLeft) {
+		gen_input.r.position = (gen_input.r.position - RADIUS_STEP).max(0.0);
+	}
+	if keys.just_pressed(KeyCode::PageUp) {
+		gen_input.shrink += SHRINK_STEP;
+	}
+	if keys.just_pressed(KeyCode::PageDown) {
+		gen_input.shrink = (gen_input.shrink - SHRINK_STEP).max(0.0);
+	}
+	let digits = [
+		KeyCode::Digit1,
+		KeyCode::Digit2,
+		KeyCode::Digit3,
+		KeyCode::Digit4,
+		KeyCode::Digit5,
+		KeyCode::Digit6,
+		KeyCode::Digit7,
+		KeyCode::Digit8,
+		KeyCode::Digit9,
+	];
+	for (key, line) in digits.iter().zip(presets.0.iter()) {
+		if keys.just_pressed(*key) {
+			*gen_input = parse_preset(line).unwrap();
+		}
+	}
+	if keys.just_pressed(KeyCode::KeyS) {
+		presets.0.push(format_preset(&gen_input));
+		if let Err(err) = std::fs::write(PRESETS_PATH, presets.0.join("\n") + "\n")
+		{
+			error!("could not save presets to {}: {}", PRESETS_PATH, err);
+		}
+	}
+}
+
+fn generate(
+	gen_input: Res<ArcPolyGenInput>,
+	mut document: ResMut<Document>,
+	mut timings: ResMut<StageTimings>,
 ) {
-	let mut arc_poly = arc_poly_query.single_mut();
 	if gen_input.is_changed() {
-		// TODO: this is probably not the right way to do it
-		let borrowed: &mut ArcPoly = arc_poly.borrow_mut();
-		*borrowed = ArcPoly::from_gen_input(&gen_input);
-	}
-	arc_poly.draw(&mut gizmos, &Color::BLUE);
-	let shrunk = arc_poly.shrunk(&mut gizmos, gen_input.shrink.max(0.0));
-	for sub_poly in shrunk {
-		sub_poly.draw(&mut gizmos, &Color::GREEN);
+		let start = Instant::now();
+		document.clear_layer(INPUT_LAYER);
+		document.add_shape(INPUT_LAYER, ArcPoly::from_gen_input(&gen_input));
+		timings.generate = start.elapsed();
+	}
+}
+
+/// Fits the camera to the input whenever it is regenerated. Runs after the
+/// camera update, which sets the projection area from the window size.
+fn frame_input(
+	gen_input: Res<ArcPolyGenInput>,
+	document: Res<Document>,
+	mut cameras: Query<(&mut OrthographicProjection, &mut Transform)>,
+) {
+	if !gen_input.is_changed() {
+		return;
+	}
+	let Some(bounds) = document.layers[INPUT_LAYER]
+		.shapes
+		.iter()
+		.map(|shape| shape.poly.bounds())
+		.reduce(|a, b| a.union(b))
+	else {
+		return;
+	};
+	for (mut projection, mut transform) in cameras.iter_mut() {
+		fit_camera_to(bounds, &mut projection, &mut transform, FRAME_MARGIN);
+	}
+}
+
+fn offset(
+	mut commands: Commands,
+	gen_input: Res<ArcPolyGenInput>,
+	mut document: ResMut<Document>,
+	mut timings: ResMut<StageTimings>,
+	pending: Query<(Entity, &PendingShrink)>,
+) {
+	if gen_input.is_changed() {
+		timings.shrink_started = Some(Instant::now());
+		for (entity, pending_shrink) in pending.iter() {
+			pending_shrink.cancellation.cancel();
+			commands.entity(entity).despawn();
+		}
+		for shape in document.layers[INPUT_LAYER].shapes.iter() {
+			let cancellation = Cancellation::default();
+			let task = shape.poly.shrunk_async(
+				gen_input.shrink.max(0.0),
+				Tolerance::default(),
+				cancellation.clone(),
+			);
+			commands.spawn(PendingShrink { task, cancellation });
+		}
+		document.clear_layer(OFFSET_LAYER);
+	}
+}
+
+/// Starts tracing the polyline offset of the input as an independent check,
+/// which takes quadratic time in the number of points and so runs on the
+/// async compute task pool like the exact offset.
+fn check_offset(
+	mut commands: Commands,
+	gen_input: Res<ArcPolyGenInput>,
+	document: Res<Document>,
+	mut warnings: ResMut<Warnings>,
+	mut traced_loops: ResMut<TracedLoops>,
+	mut timings: ResMut<StageTimings>,
+	pending: Query<Entity, With<PendingTrace>>,
+) {
+	if gen_input.is_changed() {
+		timings.trace_started = Some(Instant::now());
+		warnings.clear();
+		traced_loops.0.clear();
+		for entity in pending.iter() {
+			commands.entity(entity).despawn();
+		}
+		let amount = gen_input.shrink.max(0.0);
+		for shape in document.layers[INPUT_LAYER].shapes.iter() {
+			let poly = shape.poly.clone();
+			let task = AsyncComputeTaskPool::get().spawn(async move {
+				approximate_traced(&poly, amount, CHECK_TOLERANCE)
+			});
+			commands.spawn(PendingTrace { shape: shape.id, task });
+		}
+	}
+}
+
+/// Collects the traces started by `check_offset` and lists the pieces they
+/// could not close.
+fn show_traces(
+	mut commands: Commands,
+	mut warnings: ResMut<Warnings>,
+	mut traced_loops: ResMut<TracedLoops>,
+	mut timings: ResMut<StageTimings>,
+	mut pending: Query<(Entity, &mut PendingTrace)>,
+) {
+	for (entity, mut trace) in pending.iter_mut() {
+		let Some(mut traced) = block_on(poll_once(&mut trace.task)) else {
+			continue;
+		};
+		for warning in traced.warnings() {
+			warnings.push(format!("{}: {}", trace.shape, warning));
+		}
+		traced_loops.0.append(&mut traced.closed_loops);
+		if let Some(started) = timings.trace_started {
+			timings.trace = started.elapsed();
+		}
+		commands.entity(entity).despawn();
+	}
+}
+
+fn concentric_offsets(
+	gen_input: Res<ArcPolyGenInput>,
+	concentric: Res<Concentric>,
+	document: Res<Document>,
+	mut offsets: ResMut<ConcentricOffsets>,
+	mut warnings: ResMut<Warnings>,
+) {
+	if gen_input.is_changed() || concentric.is_changed() {
+		offsets.0.clear();
+		if concentric.enabled && concentric.step > 0.0 {
+			for shape in document.layers[INPUT_LAYER].shapes.iter() {
+				let rings = match shape.poly.shrunk_concentric(
+					concentric.step,
+					concentric.count,
+					&Tolerance::default(),
+				) {
+					Ok(rings) => rings,
+					Err(err) => {
+						warnings.push(format!(
+							"{}: concentric offsets failed: {}",
+							shape.id, err
+						));
+						continue;
+					}
+				};
+				for (i, ring) in rings.into_iter().enumerate() {
+					if offsets.0.len() <= i {
+						offsets.0.push(vec![]);
+					}
+					offsets.0[i].extend(ring);
+				}
+			}
+		}
+	}
+}
+
+fn draw_concentric(
+	mut gizmos: Gizmos,
+	concentric: Res<Concentric>,
+	offsets: Res<ConcentricOffsets>,
+) {
+	if !concentric.enabled {
+		return;
+	}
+	let background = ClearColor::default().0;
+	for (i, ring) in offsets.0.iter().enumerate() {
+		let color = Palette::Viridis.color_on(i, background);
+		for poly in ring {
+			poly.draw(&mut gizmos, &color);
+		}
+	}
+}
+
+fn upcoming_collisions(
+	gen_input: Res<ArcPolyGenInput>,
+	events: Res<CollisionEvents>,
+	document: Res<Document>,
+	mut upcoming: ResMut<UpcomingCollisions>,
+) {
+	if gen_input.is_changed() || events.is_changed() {
+		upcoming.0.clear();
+		if events.enabled {
+			// Shapes which cannot be offset are reported by the offset itself.
+			for shape in document.layers[INPUT_LAYER].shapes.iter() {
+				if let Ok(collisions) = shape
+					.poly
+					.future_collisions(&FloatBackend::default(), &Tolerance::default())
+				{
+					upcoming.0.extend(collisions);
+				}
+			}
+			upcoming.0.sort_by(|a, b| a.time_place.f.total_cmp(&b.time_place.f));
+			upcoming.0.truncate(events.count);
+		}
+	}
+}
+
+fn draw_collisions(
+	mut gizmos: Gizmos,
+	events: Res<CollisionEvents>,
+	upcoming: Res<UpcomingCollisions>,
+) {
+	if !events.enabled {
+		return;
+	}
+	let background = ClearColor::default().0;
+	for (i, collision) in upcoming.0.iter().enumerate() {
+		collision
+			.draw_gizmos(&mut gizmos, &Palette::OkabeIto.color_on(i, background));
+	}
+}
+
+fn draw_comparison(
+	mut gizmos: Gizmos,
+	comparison: Res<Comparison>,
+	traced_loops: Res<TracedLoops>,
+	document: Res<Document>,
+) {
+	if !comparison.enabled {
+		return;
+	}
+	let exact = &document.layers[OFFSET_LAYER].shapes;
+	for pts in traced_loops.0.iter() {
+		let shifted = pts.iter().map(|p| *p + comparison.shift);
+		gizmos.linestrip_2d(shifted.clone().chain(shifted.take(1)), Color::ORANGE);
+		for p in pts.iter() {
+			let distance = exact
+				.iter()
+				.filter_map(|shape| shape.poly.closest_point(p))
+				.map(|(_, q)| (q - *p).length())
+				.fold(f32::INFINITY, f32::min);
+			if distance > comparison.highlight_distance {
+				gizmos.circle_2d(*p + comparison.shift, 3.0, Color::RED);
+			}
+		}
+	}
+}
+
+fn show_offset(
+	mut commands: Commands,
+	mut document: ResMut<Document>,
+	mut timings: ResMut<StageTimings>,
+	shrunk: Query<(Entity, &Shrunk)>,
+) {
+	for (entity, Shrunk(polys)) in shrunk.iter() {
+		if let Some(started) = timings.shrink_started {
+			timings.shrink = started.elapsed();
+		}
+		for sub_poly in polys {
+			document.add_shape(OFFSET_LAYER, sub_poly.clone());
+		}
+		commands.entity(entity).despawn();
+	}
+}
+
+fn update_timing_hud(
+	timings: Res<StageTimings>,
+	mut hud: Query<&mut Text, With<TimingHud>>,
+) {
+	if !timings.is_changed() {
+		return;
+	}
+	let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+	for mut text in hud.iter_mut() {
+		text.sections[0].value = format!(
+			"generate {:.2} ms\nshrink {:.2} ms\ntrace {:.2} ms",
+			ms(timings.generate),
+			ms(timings.shrink),
+			ms(timings.trace)
+		);
 	}
 }
@@ -1,12 +1,13 @@
 extern crate derive_more;
 use derive_more::{Add, Display, Sub};
 use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 use std::f32::consts::PI;
 
 use bevy::{
 	ecs::component::Component,
-	math::{Mat2, Mat3, Vec2, Vec3},
+	math::{DVec2, Mat2, Vec2},
 	reflect::Reflect,
 };
 
@@ -14,7 +15,12 @@ pub fn midpoint(a: &Vec2, b: &Vec2) -> Vec2 {
 	0.5 * (*a + *b)
 }
 
+/// Real roots of `a * x^2 + b * x + c` in increasing order, solving the
+/// linear equation when `a` is zero.
 pub fn second_deg_eq(a: f32, b: f32, c: f32) -> Vec<f32> {
+	if a == 0.0 {
+		return if b == 0.0 { vec![] } else { vec![-c / b] };
+	}
 	let d = b.powi(2) - 4.0 * a * c;
 	if d < 0.0 {
 		Vec::new()
@@ -27,17 +33,81 @@ pub fn second_deg_eq(a: f32, b: f32, c: f32) -> Vec<f32> {
 	}
 }
 
-pub fn circle_center_from_3_points(p1: &Vec2, p2: &Vec2, p3: &Vec2) -> Vec2 {
-	let c1 =
-		Vec3::new(p1.length_squared(), p2.length_squared(), p3.length_squared());
-	let c2 = Vec3::new(p1.x, p2.x, p3.x);
-	let c3 = Vec3::new(p1.y, p2.y, p3.y);
+/// Values which may hold NaN or infinite components.
+pub trait Finite {
+	fn all_finite(&self) -> bool;
+}
+
+impl Finite for f32 {
+	fn all_finite(&self) -> bool {
+		self.is_finite()
+	}
+}
+
+impl Finite for Vec2 {
+	fn all_finite(&self) -> bool {
+		self.is_finite()
+	}
+}
+
+impl Finite for FloatVec2 {
+	fn all_finite(&self) -> bool {
+		self.f.is_finite() && self.v.is_finite()
+	}
+}
+
+impl<T: Finite> Finite for Vec<T> {
+	fn all_finite(&self) -> bool {
+		self.iter().all(Finite::all_finite)
+	}
+}
+
+impl<S: Finite, T: Finite> Finite for (S, T) {
+	fn all_finite(&self) -> bool {
+		self.0.all_finite() && self.1.all_finite()
+	}
+}
+
+impl<T: Finite> Finite for Option<T> {
+	fn all_finite(&self) -> bool {
+		match self {
+			Some(value) => value.all_finite(),
+			None => true,
+		}
+	}
+}
 
-	let m1 = Mat3::from_cols(c2, c3, Vec3::ONE);
-	let m2 = Mat3::from_cols(c1, c3, Vec3::ONE);
-	let m3 = Mat3::from_cols(c1, c2, Vec3::ONE);
+/// `value`, which with the `checked-math` feature must be finite or this
+/// panics naming `operation` and its `inputs`, so that NaNs are caught where
+/// they arise rather than when geometry goes missing.
+#[cfg(feature = "checked-math")]
+pub fn checked<T: Finite>(
+	operation: &str,
+	inputs: impl FnOnce() -> String,
+	value: T,
+) -> T {
+	if !value.all_finite() {
+		panic!("{} gave a non-finite result for {}", operation, inputs());
+	}
+	value
+}
+
+#[cfg(not(feature = "checked-math"))]
+#[inline(always)]
+pub fn checked<T>(_: &str, _: impl FnOnce() -> String, value: T) -> T {
+	value
+}
 
-	Vec2::new(m2.determinant(), -m3.determinant()) * 0.5 / m1.determinant()
+/// Center of the circle through the three points. It is solved relative to
+/// `p1` in `f64`, which keeps nearly collinear points far from the origin,
+/// i.e. flat arcs, from losing the center to cancellation.
+pub fn circle_center_from_3_points(p1: &Vec2, p2: &Vec2, p3: &Vec2) -> Vec2 {
+	let origin = p1.as_dvec2();
+	let (b, c) = (p2.as_dvec2() - origin, p3.as_dvec2() - origin);
+	let (b2, c2) = (b.length_squared(), c.length_squared());
+	let center = DVec2::new(c.y * b2 - b.y * c2, b.x * c2 - c.x * b2)
+		/ (2.0 * b.perp_dot(c));
+	(origin + center).as_vec2()
 }
 
 #[derive(Clone, Component, Copy, Display, Add, Reflect, Sub)]
@@ -49,6 +119,408 @@ pub struct FloatVec2 {
 
 pub type Circle = FloatVec2;
 
+#[derive(Clone, Copy, Debug, Display, Reflect, PartialEq, Eq)]
+pub enum TangencyKind {
+	/// The circles touch from outside of each other.
+	External,
+	/// One circle touches the other from inside.
+	Internal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircleIntersection {
+	Disjoint,
+	Tangent(Vec2),
+	Crossing(Vec2, Vec2),
+	/// Same center but different radii.
+	Concentric,
+	/// The same circle, which intersects itself everywhere.
+	Coincident,
+}
+
+impl CircleIntersection {
+	pub fn points(&self) -> Vec<Vec2> {
+		match self {
+			CircleIntersection::Tangent(p) => vec![*p],
+			CircleIntersection::Crossing(p, q) => vec![*p, *q],
+			_ => vec![],
+		}
+	}
+}
+
+impl Circle {
+	pub fn contains_point(&self, p: &Vec2) -> bool {
+		(*p - self.v).length() <= self.f
+	}
+
+	pub fn contains_circle(&self, other: &Circle) -> bool {
+		(other.v - self.v).length() + other.f <= self.f
+	}
+
+	/// Kind of tangency with `other` when the circles touch within `tol`.
+	/// Concentric circles are never tangent.
+	pub fn tangent_to(&self, other: &Circle, tol: f32) -> Option<TangencyKind> {
+		let d = (other.v - self.v).length();
+		if (d - (self.f + other.f)).abs() <= tol {
+			Some(TangencyKind::External)
+		} else if d > tol && (d - (self.f - other.f).abs()).abs() <= tol {
+			Some(TangencyKind::Internal)
+		} else {
+			None
+		}
+	}
+
+	/// Intersection with `other`, where circles within `tol` of touching are
+	/// considered tangent.
+	pub fn intersect(&self, other: &Circle, tol: f32) -> CircleIntersection {
+		let intersection = self.intersect_unchecked(other, tol);
+		// Gathering the points allocates, so it is only done to check them.
+		#[cfg(feature = "checked-math")]
+		checked(
+			"Circle::intersect",
+			|| format!("{}, {}, {}", self, other, tol),
+			intersection.points(),
+		);
+		intersection
+	}
+
+	fn intersect_unchecked(
+		&self,
+		other: &Circle,
+		tol: f32,
+	) -> CircleIntersection {
+		let (a, b) = (self, other);
+		let d = (a.v - b.v).length();
+		if d <= tol {
+			return if (a.f - b.f).abs() <= tol {
+				CircleIntersection::Coincident
+			} else {
+				CircleIntersection::Concentric
+			};
+		}
+		let dir = (b.v - a.v) / d;
+		let external = d - (a.f + b.f);
+		let internal = (a.f - b.f).abs() - d;
+		if external > tol || internal > tol {
+			CircleIntersection::Disjoint
+		} else if external.abs() <= tol || (internal.abs() <= tol && a.f >= b.f) {
+			CircleIntersection::Tangent(a.v + dir * a.f)
+		} else if internal.abs() <= tol {
+			CircleIntersection::Tangent(a.v - dir * a.f)
+		} else {
+			let alpha = (a.f.powi(2) - b.f.powi(2) + d.powi(2)) / (2.0 * d);
+			let h = (a.f.powi(2) - alpha.powi(2)).max(0.0).sqrt();
+			let v2 = a.v + alpha * dir;
+			let mut v3 = Mat2::from_cols(Vec2::Y, Vec2::X) * (h * dir);
+			v3.y *= -1.0;
+			CircleIntersection::Crossing(v2 + v3, v2 - v3)
+		}
+	}
+
+	/// Common tangents with `other` as pairs of touching points, on `self`
+	/// and on `other`, external tangents first. The points of a tangent
+	/// coincide where the circles touch, the line then being perpendicular to
+	/// the line through the centers.
+	pub fn tangent_lines(&self, other: &Circle) -> Vec<(Vec2, Vec2)> {
+		let d = other.v - self.v;
+		let dist = d.length();
+		if dist == 0.0 {
+			return vec![];
+		}
+		let dir = d / dist;
+		let mut res: Vec<(Vec2, Vec2)> = vec![];
+		for other_r in [other.f, -other.f] {
+			// Unit normals `n` with signed distances `self.f` and `other_r`
+			// of the centers to the tangent, which pass `v - r * n`.
+			let c = (other_r - self.f) / dist;
+			if c.abs() > 1.0 {
+				continue;
+			}
+			let h = (1.0 - c * c).sqrt();
+			let signs: &[f32] = if h == 0.0 { &[1.0] } else { &[1.0, -1.0] };
+			for sign in signs {
+				let n = c * dir + sign * h * dir.perp();
+				res.push((self.v - self.f * n, other.v - other_r * n));
+			}
+		}
+		res
+	}
+
+	/// Shortest distance between the two circles as curves, which is zero
+	/// when they intersect.
+	pub fn distance_to(&self, other: &Circle) -> f32 {
+		let d = (other.v - self.v).length();
+		(d - (self.f + other.f)).max((self.f - other.f).abs() - d).max(0.0)
+	}
+}
+
+/// Numeric kernel of the intersections that the combinatorial algorithms,
+/// like `ArcPoly::shrunk_with`, are built on. Implement it to substitute e.g.
+/// exact arithmetic or to instrument the queries.
+pub trait IntersectionBackend {
+	fn circle_circle(&self, a: &Circle, b: &Circle) -> CircleIntersection;
+
+	/// Circles tangent to `a`, `b` and `c`, as by `three_circle_collision`.
+	fn three_circles(&self, a: &Circle, b: &Circle, c: &Circle) -> Vec<Circle>;
+}
+
+/// The `f32` kernel of this module, where circles within `tolerance` of
+/// touching are considered tangent.
+#[derive(Clone, Copy, Default, Reflect)]
+pub struct FloatBackend {
+	pub tolerance: f32,
+}
+
+impl IntersectionBackend for FloatBackend {
+	fn circle_circle(&self, a: &Circle, b: &Circle) -> CircleIntersection {
+		a.intersect(b, self.tolerance)
+	}
+
+	fn three_circles(&self, a: &Circle, b: &Circle, c: &Circle) -> Vec<Circle> {
+		three_circle_collision(a, b, c)
+	}
+}
+
+/// Thresholds of the geometry code, grouped into profiles for the scale of
+/// the coordinates, as a threshold suited to pixels is far too coarse for
+/// millimeters and drowned by rounding for geographic coordinates.
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+#[display(
+	fmt = "tolerance({}, {}, {}, {}, {}, {}, {}, {})",
+	min_radius,
+	min_span,
+	min_length,
+	merge_distance,
+	boundary_distance,
+	side_offset,
+	collision_distance,
+	event_margin
+)]
+pub struct Tolerance {
+	/// Smallest radius of a valid arc.
+	pub min_radius: f32,
+	/// Smallest angle a valid arc may sweep.
+	pub min_span: f32,
+	/// Smallest length of a valid straight segment.
+	pub min_length: f32,
+	/// Distance within which points are identified, e.g. when cutting and
+	/// stitching boundaries.
+	pub merge_distance: f32,
+	/// Distance within which points are classified as on a boundary.
+	pub boundary_distance: f32,
+	/// Distance from a boundary at which the regions on either side of it are
+	/// probed, well above `merge_distance`.
+	pub side_offset: f32,
+	/// Distance within which both ends of a shrinking segment must come to
+	/// the point where its circle meets those of its neighbours for the
+	/// segment to vanish there, rather than the circles meeting elsewhere
+	/// after the segment was cut off by another event or on the far side of
+	/// an arc.
+	pub collision_distance: f32,
+	/// Offset before or after a collision event at which the shrinking
+	/// boundary is inspected, so that the segments taking part are resolved
+	/// apart despite rounding. Well below `merge_distance`, within which
+	/// events happen at the same offset.
+	pub event_margin: f32,
+}
+
+impl Default for Tolerance {
+	fn default() -> Self {
+		Tolerance::pixel()
+	}
+}
+
+impl Tolerance {
+	/// For screen coordinates in pixels, as used by the demo.
+	pub fn pixel() -> Self {
+		Tolerance {
+			min_radius: 1e-3,
+			min_span: 1e-5,
+			min_length: 1e-4,
+			merge_distance: 1e-3,
+			boundary_distance: 1e-3,
+			side_offset: 1e-2,
+			collision_distance: 1.0,
+			event_margin: 1e-4,
+		}
+	}
+
+	/// The same thresholds for coordinates `factor` times as large. Angles
+	/// are kept.
+	pub fn scaled(&self, factor: f32) -> Self {
+		Tolerance {
+			min_radius: factor * self.min_radius,
+			min_span: self.min_span,
+			min_length: factor * self.min_length,
+			merge_distance: factor * self.merge_distance,
+			boundary_distance: factor * self.boundary_distance,
+			side_offset: factor * self.side_offset,
+			collision_distance: factor * self.collision_distance,
+			event_margin: factor * self.event_margin,
+		}
+	}
+
+	/// For drawings in millimeters, with parts up to about a meter.
+	pub fn cad_mm() -> Self {
+		Tolerance {
+			min_radius: 1e-4,
+			min_span: 1e-5,
+			min_length: 1e-5,
+			merge_distance: 1e-4,
+			boundary_distance: 1e-4,
+			side_offset: 1e-3,
+			collision_distance: 0.1,
+			event_margin: 1e-5,
+		}
+	}
+
+	/// For projected geographic coordinates in meters, which reach hundreds
+	/// of kilometers where `f32` resolves only centimeters.
+	pub fn geo_meters() -> Self {
+		Tolerance {
+			min_radius: 0.1,
+			min_span: 1e-5,
+			min_length: 0.1,
+			merge_distance: 0.1,
+			boundary_distance: 0.1,
+			side_offset: 1.0,
+			collision_distance: 10.0,
+			event_margin: 1e-2,
+		}
+	}
+}
+
+/// Uniform scale and rotation about the origin followed by a translation.
+/// Orientation is preserved, so arcs keep their bend.
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+#[display(fmt = "xform({}, {}, {})", scale, rotation, translation)]
+pub struct Xform2 {
+	pub scale: f32,
+	pub rotation: f32,
+	pub translation: Vec2,
+}
+
+impl Default for Xform2 {
+	fn default() -> Self {
+		Xform2::IDENTITY
+	}
+}
+
+impl Xform2 {
+	pub const IDENTITY: Xform2 =
+		Xform2 { scale: 1.0, rotation: 0.0, translation: Vec2::ZERO };
+
+	pub fn from_translation(translation: Vec2) -> Self {
+		Xform2 { translation, ..Xform2::IDENTITY }
+	}
+
+	pub fn apply(&self, point: &Vec2) -> Vec2 {
+		self.scale * Vec2::from_angle(self.rotation).rotate(*point)
+			+ self.translation
+	}
+
+	/// The transform applying `other` first and then `self`.
+	pub fn compose(&self, other: &Xform2) -> Xform2 {
+		Xform2 {
+			scale: self.scale * other.scale,
+			rotation: self.rotation + other.rotation,
+			translation: self.apply(&other.translation),
+		}
+	}
+
+	pub fn inverse(&self) -> Xform2 {
+		let scale = 1.0 / self.scale;
+		Xform2 {
+			scale,
+			rotation: -self.rotation,
+			translation: -scale
+				* Vec2::from_angle(-self.rotation).rotate(self.translation),
+		}
+	}
+
+	pub fn apply_to_circle(&self, circle: &Circle) -> Circle {
+		FloatVec2 { f: self.scale.abs() * circle.f, v: self.apply(&circle.v) }
+	}
+}
+
+/// Similarity transformations and mirroring of geometry.
+pub trait Transform2 {
+	fn apply_xform(&mut self, xform: &Xform2);
+
+	/// Reflects across the line through `origin` along `dir`. Arcs have their
+	/// bend flipped as the reflection reverses their direction of travel.
+	fn mirror(&mut self, origin: Vec2, dir: Vec2);
+
+	fn translate(&mut self, offset: Vec2) {
+		self.apply_xform(&Xform2::from_translation(offset));
+	}
+
+	fn rotate_about(&mut self, pivot: Vec2, angle: f32) {
+		self.apply_xform(&Xform2 {
+			scale: 1.0,
+			rotation: angle,
+			translation: pivot - Vec2::from_angle(angle).rotate(pivot),
+		});
+	}
+
+	fn scale_about(&mut self, pivot: Vec2, factor: f32) {
+		self.apply_xform(&Xform2 {
+			scale: factor,
+			rotation: 0.0,
+			translation: pivot - factor * pivot,
+		});
+	}
+}
+
+/// Equality up to `epsilon` in every coordinate, radius and angle.
+pub trait ApproxEq {
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool;
+}
+
+impl ApproxEq for f32 {
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		(self - other).abs() <= epsilon
+	}
+}
+
+impl ApproxEq for Vec2 {
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		self.abs_diff_eq(*other, epsilon)
+	}
+}
+
+impl ApproxEq for Circle {
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		self.f.approx_eq(&other.f, epsilon) && self.v.approx_eq(&other.v, epsilon)
+	}
+}
+
+impl ApproxEq for Xform2 {
+	/// Rotations are compared modulo a full turn.
+	fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+		let turn = (self.rotation - other.rotation).rem_euclid(2.0 * PI);
+		self.scale.approx_eq(&other.scale, epsilon)
+			&& turn.min(2.0 * PI - turn) <= epsilon
+			&& self.translation.approx_eq(&other.translation, epsilon)
+	}
+}
+
+pub fn mirror_point(point: &Vec2, origin: Vec2, dir: Vec2) -> Vec2 {
+	let d = dir.normalize();
+	let v = *point - origin;
+	origin + 2.0 * v.dot(d) * d - v
+}
+
+impl Transform2 for Circle {
+	fn apply_xform(&mut self, xform: &Xform2) {
+		*self = xform.apply_to_circle(self);
+	}
+
+	fn mirror(&mut self, origin: Vec2, dir: Vec2) {
+		self.v = mirror_point(&self.v, origin, dir);
+	}
+}
+
 pub fn angle_counter_clockwise(a: &Vec2, b: &Vec2) -> f32 {
 	(Mat2::from_cols(*a, *b).determinant().atan2(a.dot(*b)) + 2.0 * PI)
 		% (2.0 * PI)
@@ -63,19 +535,121 @@ pub fn bool_to_sign(b: bool) -> f32 {
 }
 
 pub fn two_circle_collision(a: &Circle, b: &Circle) -> Vec<Vec2> {
-	let d = (a.v - b.v).length();
-	if d > a.f + b.f || d < f32::abs(a.f - b.f) || d == 0.0 {
-		Vec::default()
-	} else if d == a.f + b.f {
-		Vec::from([a.v + (b.v - a.v).normalize() * a.f])
+	a.intersect(b, 0.0).points()
+}
+
+/// Relative slack in squared distances under which a line counts as touching
+/// a circle, absorbing the rounding of computing the closest approach.
+const TANGENT_EPSILON: f32 = 1e-5;
+
+/// Parameters `t`, in increasing order, at which `origin + t * dir` meets
+/// `circle`. A line touching the circle, within `TANGENT_EPSILON` relative to
+/// the squared radius, yields the single parameter of its closest approach.
+/// Solving from the closest approach rather than the general quadratic avoids
+/// the cancellation that otherwise splits a tangent into two nearby points or
+/// loses it altogether.
+pub fn circle_line_intersection(
+	circle: &Circle,
+	origin: &Vec2,
+	dir: &Vec2,
+) -> Vec<f32> {
+	let dir_squared = dir.length_squared();
+	if dir_squared == 0.0 {
+		return vec![];
+	}
+	let o = *origin - circle.v;
+	let closest = -o.dot(*dir) / dir_squared;
+	let r_squared = circle.f.powi(2);
+	let h_squared = r_squared - (o + closest * *dir).length_squared();
+	let ts = if h_squared.abs() <= TANGENT_EPSILON * r_squared {
+		vec![closest]
+	} else if h_squared < 0.0 {
+		vec![]
 	} else {
-		let alpha = (a.f.powi(2) - b.f.powi(2) + d.powi(2)) / (2.0 * d);
-		let h = (a.f.powi(2) - alpha.powi(2)).sqrt();
-		let v2 = a.v + alpha * (b.v - a.v) / d;
-		let mut v3 = Mat2::from_cols(Vec2::Y, Vec2::X) * (h * (b.v - a.v) / d);
-		v3.y *= -1.0;
-		Vec::from([v2 + v3, v2 - v3])
+		let half = (h_squared / dir_squared).sqrt();
+		vec![closest - half, closest + half]
+	};
+	checked(
+		"circle_line_intersection",
+		|| format!("{}, {}, {}", circle, origin, dir),
+		ts,
+	)
+}
+
+/// Parameters `(s, t)` at which the lines `a + s * a_dir` and
+/// `b + t * b_dir` cross, or `None` when they are parallel.
+pub fn line_line_intersection(
+	a: &Vec2,
+	a_dir: &Vec2,
+	b: &Vec2,
+	b_dir: &Vec2,
+) -> Option<(f32, f32)> {
+	let denominator = a_dir.perp_dot(*b_dir);
+	if denominator == 0.0 {
+		return None;
+	}
+	let ab = *b - *a;
+	checked(
+		"line_line_intersection",
+		|| format!("{}, {}, {}, {}", a, a_dir, b, b_dir),
+		Some((
+			ab.perp_dot(*b_dir) / denominator,
+			ab.perp_dot(*a_dir) / denominator,
+		)),
+	)
+}
+
+/// Relative slack in the radius within which a point counts as enclosed by
+/// `min_enclosing_circle`, so that rounding does not trigger needless growth.
+const ENCLOSING_EPSILON: f32 = 1e-5;
+
+/// Smallest circle containing all `points`, by Welzl's algorithm in its
+/// iterative form, or `None` for no points. The points are visited in a fixed
+/// pseudo-random order, which keeps the expected running time linear also for
+/// points sampled in order along a boundary.
+pub fn min_enclosing_circle(points: &[Vec2]) -> Option<Circle> {
+	let mut points = points.to_vec();
+	points.shuffle(&mut StdRng::seed_from_u64(0));
+	let encloses = |c: &Circle, p: &Vec2| {
+		(*p - c.v).length() <= c.f * (1.0 + ENCLOSING_EPSILON)
+	};
+	let mut circle = FloatVec2 { f: 0.0, v: *points.first()? };
+	for i in 1..points.len() {
+		if encloses(&circle, &points[i]) {
+			continue;
+		}
+		circle = FloatVec2 { f: 0.0, v: points[i] };
+		for j in 0..i {
+			if encloses(&circle, &points[j]) {
+				continue;
+			}
+			circle = circle_from_diameter(&points[i], &points[j]);
+			for k in 0..j {
+				if !encloses(&circle, &points[k]) {
+					circle = circle_through(&points[i], &points[j], &points[k]);
+				}
+			}
+		}
 	}
+	Some(circle)
+}
+
+fn circle_from_diameter(a: &Vec2, b: &Vec2) -> Circle {
+	FloatVec2 { f: 0.5 * (*b - *a).length(), v: midpoint(a, b) }
+}
+
+/// Circumcircle of the three points, or for collinear points the circle
+/// having the two farthest apart as diameter.
+fn circle_through(a: &Vec2, b: &Vec2, c: &Vec2) -> Circle {
+	let center = circle_center_from_3_points(a, b, c);
+	if center.is_finite() {
+		return FloatVec2 { f: (*a - center).length(), v: center };
+	}
+	[(a, b), (b, c), (a, c)]
+		.into_iter()
+		.map(|(p, q)| circle_from_diameter(p, q))
+		.max_by(|p, q| p.f.total_cmp(&q.f))
+		.unwrap()
 }
 
 pub fn three_circle_collision(
@@ -85,14 +659,59 @@ pub fn three_circle_collision(
 ) -> Vec<FloatVec2> {
 	let a_ = *a - *c;
 	let b_ = *b - *c;
-	three_circle_collision_0(&a_, &b_)
-		.iter()
-		.map(|col| FloatVec2 { f: col.f - c.f, v: col.v + c.v })
-		.collect_vec()
+	checked(
+		"three_circle_collision",
+		|| format!("{}, {}, {}", a, b, c),
+		three_circle_collision_0(&a_, &b_)
+			.iter()
+			.map(|col| FloatVec2 { f: col.f - c.f, v: col.v + c.v })
+			.collect_vec(),
+	)
+}
+
+/// A circle tangent to three given circles together with the kind of
+/// tangency to each of them.
+#[derive(Clone, Copy)]
+pub struct ApolloniusCircle {
+	pub circle: Circle,
+	pub kinds: [TangencyKind; 3],
+}
+
+/// All, up to eight, circles tangent to each of `a`, `b` and `c`. Each choice
+/// of external or internal tangency per input circle is solved by flipping the
+/// sign of its radius in `three_circle_collision`, keeping the solutions of
+/// positive radius. Those for collinear centers come in pairs mirrored in
+/// the line through them.
+pub fn apollonius(a: &Circle, b: &Circle, c: &Circle) -> Vec<ApolloniusCircle> {
+	let kind = |external: bool| match external {
+		true => TangencyKind::External,
+		false => TangencyKind::Internal,
+	};
+	let signed = |circle: &Circle, external: bool| FloatVec2 {
+		f: bool_to_sign(external) * circle.f,
+		v: circle.v,
+	};
+	let mut res: Vec<ApolloniusCircle> = vec![];
+	for flags in 0..8 {
+		let ext = [flags & 1 == 0, flags & 2 == 0, flags & 4 == 0];
+		for circle in three_circle_collision(
+			&signed(a, ext[0]),
+			&signed(b, ext[1]),
+			&signed(c, ext[2]),
+		) {
+			if circle.f > 0.0 && circle.f.is_finite() && circle.v.is_finite() {
+				res.push(ApolloniusCircle { circle, kinds: ext.map(kind) });
+			}
+		}
+	}
+	res
 }
 
 fn three_circle_collision_0(a: &Circle, b: &Circle) -> Vec<FloatVec2> {
 	let m = Mat2::from_cols(a.v, b.v).transpose();
+	if m.determinant() == 0.0 {
+		return three_circle_collision_collinear(a, b);
+	}
 	let alpha = 1.0 / (2.0 * m.determinant());
 	let beta_a = a.v.length_squared() - a.f.powi(2);
 	let beta_b = b.v.length_squared() - b.f.powi(2);
@@ -113,3 +732,95 @@ fn three_circle_collision_0(a: &Circle, b: &Circle) -> Vec<FloatVec2> {
 		})
 		.collect_vec()
 }
+
+/// `three_circle_collision_0` for centers on a line through the origin,
+/// solved in a frame aligned with it. The position along the line and the
+/// radius then follow from the linear equations alone, and the solutions lie
+/// on either side of the line at the same distance. Coincident centers and
+/// equations without a unique solution, such as for equal radii, yield none.
+fn three_circle_collision_collinear(a: &Circle, b: &Circle) -> Vec<FloatVec2> {
+	let longer =
+		if a.v.length_squared() >= b.v.length_squared() { a.v } else { b.v };
+	let Some(dir) = longer.try_normalize() else {
+		return vec![];
+	};
+	let (p, q) = (a.v.dot(dir), b.v.dot(dir));
+	let beta_a = a.v.length_squared() - a.f.powi(2);
+	let beta_b = b.v.length_squared() - b.f.powi(2);
+	let gamma_a = -2.0 * a.f;
+	let gamma_b = -2.0 * b.f;
+	// `2 p x - gamma_a t = beta_a` and `2 q x - gamma_b t = beta_b` for the
+	// position `x` along the line and the radius `t`.
+	let det = 2.0 * (q * gamma_a - p * gamma_b);
+	if det == 0.0 {
+		return vec![];
+	}
+	let x = (gamma_a * beta_b - gamma_b * beta_a) / det;
+	let t = 2.0 * (p * beta_b - q * beta_a) / det;
+	let h_squared = t.powi(2) - x.powi(2);
+	let heights = if h_squared.abs() <= TANGENT_EPSILON * t.powi(2) {
+		vec![0.0]
+	} else if h_squared < 0.0 {
+		vec![]
+	} else {
+		let h = h_squared.sqrt();
+		vec![-h, h]
+	};
+	heights
+		.into_iter()
+		.map(|h| FloatVec2 { f: t, v: x * dir + h * dir.perp() })
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn second_deg_eq_solves_linear_equations() {
+		assert_eq!(second_deg_eq(0.0, 2.0, -4.0), vec![2.0]);
+		assert_eq!(second_deg_eq(0.0, 0.0, 1.0), Vec::<f32>::new());
+		assert_eq!(second_deg_eq(1.0, 0.0, -4.0), vec![-2.0, 2.0]);
+	}
+
+	#[test]
+	fn apollonius_solves_collinear_centers() {
+		let circle = |x: f32, r: f32| Circle { f: r, v: Vec2::new(x, 1.0) };
+		let inputs = [circle(-6.0, 1.0), circle(0.0, 2.0), circle(5.0, 1.5)];
+		let solutions = apollonius(&inputs[0], &inputs[1], &inputs[2]);
+		assert!(!solutions.is_empty());
+		for solution in &solutions {
+			let found = solution.circle;
+			for (input, kind) in inputs.iter().zip(solution.kinds) {
+				let distance = (found.v - input.v).length();
+				let expected = match kind {
+					TangencyKind::External => found.f + input.f,
+					TangencyKind::Internal => (found.f - input.f).abs(),
+				};
+				assert!((distance - expected).abs() < 1e-3 * expected);
+			}
+			let mirrored = Vec2::new(found.v.x, 2.0 - found.v.y);
+			assert!(solutions.iter().any(|other| {
+				other.kinds == solution.kinds
+					&& other.circle.approx_eq(&Circle { v: mirrored, ..found }, 1e-3)
+			}));
+		}
+		let equal = [circle(-5.0, 1.0), circle(0.0, 1.0), circle(5.0, 1.0)];
+		assert!(three_circle_collision(&equal[0], &equal[1], &equal[2]).is_empty());
+	}
+
+	#[test]
+	fn approx_eq_compares_within_epsilon() {
+		assert!(1.0f32.approx_eq(&1.05, 0.1));
+		assert!(!1.0f32.approx_eq(&1.2, 0.1));
+		assert!(Vec2::new(1.0, 2.0).approx_eq(&Vec2::new(1.05, 1.95), 0.1));
+		assert!(!Vec2::new(1.0, 2.0).approx_eq(&Vec2::new(1.0, 2.2), 0.1));
+		let circle = Circle { f: 1.0, v: Vec2::ZERO };
+		assert!(circle.approx_eq(&Circle { f: 1.05, ..circle }, 0.1));
+		assert!(!circle.approx_eq(&Circle { v: Vec2::X, ..circle }, 0.1));
+		let xform = Xform2 { rotation: PI - 1e-3, ..Xform2::IDENTITY };
+		let turned = Xform2 { rotation: -PI + 1e-3, ..Xform2::IDENTITY };
+		assert!(xform.approx_eq(&turned, 1e-2));
+		assert!(!xform.approx_eq(&Xform2::IDENTITY, 1e-2));
+	}
+}
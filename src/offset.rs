@@ -0,0 +1,480 @@
+use std::time::{Duration, Instant};
+
+use bevy::math::Vec2;
+use itertools::Itertools;
+
+use crate::{
+	geom::{
+		arc_poly::{ArcPoly, ShrinkError},
+		segment::{Bend, Segment},
+	},
+	math::{circle_center_from_3_points, FloatBackend, Tolerance},
+	util::Cancellation,
+};
+
+/// Slack of the distance check on approximate offsets, as a fraction of the
+/// offset amount.
+const DISTANCE_SLACK: f32 = 1e-3;
+
+pub enum BudgetedOffset {
+	Exact(Vec<ArcPoly>),
+	/// Polylines within the flattening tolerance of the exact offset.
+	Approximate(Vec<Vec<Vec2>>),
+}
+
+/// Inward offset of `poly` by `amount`, computed exactly with the thresholds
+/// of `shrink_tolerance` if that finishes within `budget` and by
+/// `approximate` with `tolerance` otherwise, which is also the fallback for
+/// loops `ArcPoly::shrunk_with` cannot offset.
+pub fn budgeted(
+	poly: &ArcPoly,
+	amount: f32,
+	budget: Duration,
+	tolerance: f32,
+	shrink_tolerance: &Tolerance,
+) -> BudgetedOffset {
+	let deadline = Instant::now() + budget;
+	let cancellation = Cancellation::default();
+	let mut check_deadline = |_| {
+		if Instant::now() > deadline {
+			cancellation.cancel();
+		}
+	};
+	let backend = FloatBackend::default();
+	match poly.shrunk_with(
+		amount,
+		&backend,
+		shrink_tolerance,
+		&cancellation,
+		&mut check_deadline,
+	) {
+		Ok(polys) => BudgetedOffset::Exact(polys),
+		Err(_) => BudgetedOffset::Approximate(approximate(poly, amount, tolerance)),
+	}
+}
+
+/// Closed polyline through the boundary of `poly`, deviating at most
+/// `tolerance` from the arcs. The first point is not repeated at the end.
+pub fn flatten(poly: &ArcPoly, tolerance: f32) -> Vec<Vec2> {
+	let mut pts: Vec<Vec2> = vec![];
+	for i in poly.indices() {
+		pts.extend(poly[i].sample(poly.end_of(i), tolerance).dropping_back(1));
+	}
+	pts
+}
+
+/// Outcome of stitching the offset pieces. Open chains are pieces which
+/// could not be joined into a loop and indicate a failure of the tracer.
+#[derive(Clone, Default)]
+pub struct TracedOffset {
+	pub closed_loops: Vec<Vec<Vec2>>,
+	/// Self-intersections of the raw offset at which each of `closed_loops`
+	/// was joined from several pieces.
+	pub junctions: Vec<Vec<Vec2>>,
+	/// Polylines including both their end points.
+	pub open_chains: Vec<Vec<Vec2>>,
+}
+
+impl TracedOffset {
+	pub fn warnings(&self) -> Vec<String> {
+		self
+			.open_chains
+			.iter()
+			.map(|pts| {
+				format!(
+					"open chain of {} points from {} to {} dropped",
+					pts.len(),
+					pts[0],
+					pts[pts.len() - 1]
+				)
+			})
+			.collect()
+	}
+}
+
+/// Inward offset of `poly` by `amount` through its flattening, see
+/// `approximate_traced`.
+pub fn approximate(
+	poly: &ArcPoly,
+	amount: f32,
+	tolerance: f32,
+) -> Vec<Vec<Vec2>> {
+	approximate_traced(poly, amount, tolerance).closed_loops
+}
+
+/// Inward offset of `poly` by `amount` through its flattening: the polygon
+/// edges are offset with round joins at reflex vertices, the raw offset is
+/// cut at its self-intersections, the pieces coming closer than `amount` to
+/// `poly` are dropped and the rest is stitched into counter-clockwise loops.
+pub fn approximate_traced(
+	poly: &ArcPoly,
+	amount: f32,
+	tolerance: f32,
+) -> TracedOffset {
+	let min_distance = amount - 2.0 * tolerance - DISTANCE_SLACK * amount;
+	let keeps_distance = |x: &Vec2| {
+		poly
+			.closest_point(x)
+			.is_some_and(|(_, c)| (c - *x).length() >= min_distance)
+	};
+	let mut res = TracedOffset::default();
+	// Sharp cusps of `poly` may flatten to slightly self-intersecting polygons.
+	for pts in split_self_intersections(flatten(poly, tolerance)) {
+		if signed_area(&pts) <= 0.0 {
+			continue;
+		}
+		let raw = raw_offset(&pts, amount, tolerance);
+		let pieces = cut_at_self_intersections(&raw)
+			.into_iter()
+			.filter(|piece| {
+				piece.pts.iter().tuple_windows().enumerate().all(|(k, (p, q))| {
+					(k == 0 || keeps_distance(p)) && keeps_distance(&(0.5 * (*p + *q)))
+				})
+			})
+			.collect_vec();
+		stitch(pieces, &mut res);
+	}
+	let (closed_loops, junctions) = res
+		.closed_loops
+		.into_iter()
+		.zip(res.junctions)
+		.filter(|(pts, _)| signed_area(pts) > 0.0)
+		.unzip();
+	TracedOffset { closed_loops, junctions, ..res }
+}
+
+/// `approximate` with each loop refitted by `refit`.
+pub fn approximate_arcs(
+	poly: &ArcPoly,
+	amount: f32,
+	tolerance: f32,
+) -> Vec<ArcPoly> {
+	approximate(poly, amount, tolerance)
+		.iter()
+		.map(|pts| refit(pts, tolerance))
+		.collect_vec()
+}
+
+/// Largest distance between the boundaries of the exact and the approximate
+/// offset of `poly`, sampled at `tolerance`, the exact one computed with the
+/// thresholds of `shrink_tolerance`. Being independent implementations, a
+/// deviation well beyond `tolerance` indicates a bug in either of them.
+/// Fails for loops `ArcPoly::shrunk` cannot offset.
+pub fn cross_check(
+	poly: &ArcPoly,
+	amount: f32,
+	tolerance: f32,
+	shrink_tolerance: &Tolerance,
+) -> Result<f32, ShrinkError> {
+	let exact = poly.shrunk(amount, shrink_tolerance)?;
+	let approx = approximate(poly, amount, tolerance);
+	if exact.is_empty() || approx.is_empty() {
+		return Ok(if exact.len() == approx.len() { 0.0 } else { f32::INFINITY });
+	}
+	let to_exact = approx.iter().flatten().map(|p| {
+		exact
+			.iter()
+			.filter_map(|q| q.closest_point(p))
+			.map(|(_, c)| (c - *p).length())
+			.fold(f32::INFINITY, f32::min)
+	});
+	let to_approx = exact.iter().flat_map(|q| flatten(q, tolerance)).map(|p| {
+		approx
+			.iter()
+			.flat_map(|pts| pts.iter().circular_tuple_windows())
+			.map(|(a, b)| distance_to_line_segment(p, *a, *b))
+			.fold(f32::INFINITY, f32::min)
+	});
+	Ok(to_exact.chain(to_approx).fold(0.0, f32::max))
+}
+
+/// Greedily fits arcs through runs of the closed polyline `pts` deviating at
+/// most `tolerance` from the points. Runs within `tolerance` of their chord,
+/// including those too short to define a circle, become straight segments.
+/// Runs ending where they start have no chord and only become a segment when
+/// all of their points are within `tolerance` of the start.
+pub fn refit(pts: &[Vec2], tolerance: f32) -> ArcPoly {
+	let n = pts.len();
+	let at = |i: usize| pts[i % n];
+	let fit = |i: usize, j: usize| {
+		let (a, b) = (at(i), at(j));
+		let chord = (b - a).try_normalize();
+		let off_chord = |p: Vec2| match chord {
+			Some(chord) => chord.perp_dot(p - a).abs(),
+			None => (p - a).length(),
+		};
+		if (i..=j).all(|k| off_chord(at(k)) <= tolerance) {
+			return Some((0.5 * (a + b), Bend::Straight));
+		}
+		let m = at((i + j) / 2);
+		let center = circle_center_from_3_points(&a, &m, &b);
+		let radius = (a - center).length();
+		((i..=j).all(|k| ((at(k) - center).length() - radius).abs() <= tolerance)
+			&& center.is_finite())
+		.then(|| {
+			let left_turn = (m - a).perp_dot(b - m) > 0.0;
+			(center, if left_turn { Bend::Outward } else { Bend::Inward })
+		})
+	};
+	let mut res = ArcPoly::default();
+	let mut i = 0;
+	while i < n {
+		// Two points are always joined by their chord.
+		let mut j = i + 1;
+		let mut best = (0.5 * (at(i) + at(j)), Bend::Straight);
+		while j < n {
+			match fit(i, j + 1) {
+				Some(found) => {
+					best = found;
+					j += 1;
+				}
+				None => break,
+			}
+		}
+		let (center, bend) = best;
+		res.segments.push(Segment {
+			initial: at(i),
+			center,
+			bend,
+			attributes: Default::default(),
+		});
+		i = j;
+	}
+	res
+}
+
+fn distance_to_line_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+	let ab = b - a;
+	let t = ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+	if t.is_finite() {
+		(a + t * ab - p).length()
+	} else {
+		(a - p).length()
+	}
+}
+
+/// Part of a closed polyline between two of its self-intersections,
+/// identified by `start` and `end`.
+struct Piece {
+	start: usize,
+	end: usize,
+	pts: Vec<Vec2>,
+}
+
+fn cut_at_self_intersections(pts: &[Vec2]) -> Vec<Piece> {
+	let n = pts.len();
+	let mut cuts: Vec<Vec<(f32, usize)>> = vec![vec![]; n];
+	let mut places: Vec<Vec2> = vec![];
+	for i in 0..n {
+		for j in i + 2..n {
+			if i == 0 && j == n - 1 {
+				continue;
+			}
+			if let Some((s, t)) =
+				segment_intersection(pts[i], pts[i + 1], pts[j], pts[(j + 1) % n])
+			{
+				cuts[i].push((s, places.len()));
+				cuts[j].push((t, places.len()));
+				places.push(pts[i] + s * (pts[i + 1] - pts[i]));
+			}
+		}
+	}
+	let Some(first) = cuts.iter().position(|c| !c.is_empty()) else {
+		return vec![Piece { start: 0, end: 0, pts: pts.to_vec() }];
+	};
+	for c in cuts.iter_mut() {
+		c.sort_by(|(s, _), (t, _)| s.total_cmp(t));
+	}
+	let mut pieces: Vec<Piece> = vec![];
+	let start = cuts[first][0].1;
+	let mut piece = Piece { start, end: start, pts: vec![places[start]] };
+	for step in 0..=n {
+		let k = (first + step) % n;
+		let segment_cuts = match step {
+			0 => &cuts[k][1..],
+			_ if step == n => &cuts[k][..1],
+			_ => &cuts[k][..],
+		};
+		for &(_, id) in segment_cuts {
+			piece.pts.push(places[id]);
+			piece.end = id;
+			pieces.push(piece);
+			piece = Piece { start: id, end: id, pts: vec![places[id]] };
+		}
+		if step < n {
+			piece.pts.push(pts[(k + 1) % n]);
+		}
+	}
+	pieces
+}
+
+/// Joins pieces ending where another one starts into closed loops.
+fn stitch(pieces: Vec<Piece>, res: &mut TracedOffset) {
+	let mut used = vec![false; pieces.len()];
+	for first in 0..pieces.len() {
+		let mut pts: Vec<Vec2> = vec![];
+		let mut junctions: Vec<Vec2> = vec![];
+		let mut current = first;
+		while !used[current] {
+			used[current] = true;
+			let piece = &pieces[current];
+			junctions.push(piece.pts[0]);
+			pts.extend_from_slice(&piece.pts[..piece.pts.len() - 1]);
+			if piece.end == pieces[first].start {
+				if junctions.len() == 1 {
+					junctions.clear();
+				}
+				res.closed_loops.push(pts);
+				res.junctions.push(junctions);
+				break;
+			}
+			match (0..pieces.len())
+				.find(|&i| !used[i] && pieces[i].start == piece.end)
+			{
+				Some(next) => current = next,
+				None => {
+					pts.push(*piece.pts.last().unwrap());
+					res.open_chains.push(pts);
+					break;
+				}
+			}
+		}
+	}
+}
+
+fn raw_offset(pts: &[Vec2], amount: f32, tolerance: f32) -> Vec<Vec2> {
+	let pts = pts
+		.iter()
+		.copied()
+		.coalesce(
+			|p, q| if (p - q).length() > f32::EPSILON { Err((p, q)) } else { Ok(p) },
+		)
+		.collect_vec();
+	let n = pts.len();
+	let mut res: Vec<Vec2> = vec![];
+	if n < 3 {
+		return res;
+	}
+	let max_step = 2.0 * (1.0 - tolerance / amount).max(-1.0).acos();
+	for i in 0..n {
+		let p = pts[i];
+		let n_prev = (p - pts[(n + i - 1) % n]).normalize().perp();
+		let n_next = (pts[(i + 1) % n] - p).normalize().perp();
+		let turn = n_prev.perp_dot(n_next);
+		if turn >= 0.0 {
+			// Rather than far reaching miters at sharp corners, add both offset
+			// edge ends and let the small inverted loop be cut away later.
+			res.push(p + amount * n_prev);
+			res.push(p + amount * n_next);
+		} else {
+			let angle = turn.atan2(n_prev.dot(n_next));
+			let steps = (angle.abs() / max_step).ceil().max(1.0) as usize;
+			for k in 0..=steps {
+				let dir =
+					Vec2::from_angle(angle * k as f32 / steps as f32).rotate(n_prev);
+				res.push(p + amount * dir);
+			}
+		}
+	}
+	res
+}
+
+fn split_self_intersections(pts: Vec<Vec2>) -> Vec<Vec<Vec2>> {
+	let mut done: Vec<Vec<Vec2>> = vec![];
+	let mut todo: Vec<Vec<Vec2>> = vec![pts];
+	while let Some(pts) = todo.pop() {
+		match first_self_intersection(&pts) {
+			Some((i, j, x)) => {
+				let mut outer = pts[..=i].to_vec();
+				outer.push(x);
+				outer.extend_from_slice(&pts[j + 1..]);
+				let mut inner = vec![x];
+				inner.extend_from_slice(&pts[i + 1..=j]);
+				todo.push(outer);
+				todo.push(inner);
+			}
+			None if pts.len() >= 3 => done.push(pts),
+			None => {}
+		}
+	}
+	done
+}
+
+fn first_self_intersection(pts: &[Vec2]) -> Option<(usize, usize, Vec2)> {
+	let n = pts.len();
+	for i in 0..n {
+		for j in i + 2..n {
+			if i == 0 && j == n - 1 {
+				continue;
+			}
+			let (a, b) = (pts[i], pts[i + 1]);
+			if let Some((s, _)) = segment_intersection(a, b, pts[j], pts[(j + 1) % n])
+			{
+				return Some((i, j, a + s * (b - a)));
+			}
+		}
+	}
+	None
+}
+
+/// Parameters along `a`-`b` and `c`-`d` of their proper crossing.
+fn segment_intersection(
+	a: Vec2,
+	b: Vec2,
+	c: Vec2,
+	d: Vec2,
+) -> Option<(f32, f32)> {
+	let denominator = (b - a).perp_dot(d - c);
+	if denominator == 0.0 {
+		return None;
+	}
+	let s = (c - a).perp_dot(d - c) / denominator;
+	let t = (c - a).perp_dot(b - a) / denominator;
+	(0.0 < s && s < 1.0 && 0.0 < t && t < 1.0).then_some((s, t))
+}
+
+pub fn signed_area(pts: &[Vec2]) -> f32 {
+	0.5
+		* pts
+			.iter()
+			.circular_tuple_windows()
+			.map(|(p, q)| p.perp_dot(*q))
+			.sum::<f32>()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn refit_handles_repeated_points() {
+		let p = Vec2::new(3.0, 4.0);
+		for pts in [vec![p], vec![p, p, p]] {
+			let poly = refit(&pts, 0.1);
+			assert!(poly
+				.segments
+				.iter()
+				.all(|s| s.initial.is_finite() && s.center.is_finite()));
+		}
+		let there_and_back = refit(&[p, Vec2::ZERO], 0.1);
+		assert_eq!(there_and_back.segments.len(), 2);
+		let corners = [
+			Vec2::ZERO,
+			Vec2::new(10.0, 0.0),
+			Vec2::new(10.0, 0.0),
+			Vec2::new(10.0, 10.0),
+			Vec2::new(0.0, 10.0),
+		];
+		let poly = refit(&corners, 0.1);
+		assert!(poly.segments.iter().all(|s| s.center.is_finite()));
+		assert!(poly.area().is_finite());
+	}
+
+	#[test]
+	fn refit_recovers_a_flattened_circle() {
+		let circle = ArcPoly::circle(Vec2::ZERO, 10.0);
+		let poly = refit(&flatten(&circle, 0.01), 0.05);
+		assert!(poly.segments.len() < 10);
+		assert!((poly.area() - circle.area()).abs() < 0.01 * circle.area());
+	}
+}
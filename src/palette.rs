@@ -0,0 +1,115 @@
+use bevy::{reflect::Reflect, render::color::Color};
+use derive_more::Display;
+
+/// Okabe-Ito colors, distinguishable under the common color vision
+/// deficiencies. Black is left out as it is the usual background or
+/// outline color.
+const OKABE_ITO: [(u8, u8, u8); 7] = [
+	(230, 159, 0),
+	(86, 180, 233),
+	(0, 158, 115),
+	(240, 228, 66),
+	(0, 114, 178),
+	(213, 94, 0),
+	(204, 121, 167),
+];
+
+/// Samples of viridis at equal steps, interpolated linearly in between.
+const VIRIDIS: [(u8, u8, u8); 9] = [
+	(68, 1, 84),
+	(71, 44, 122),
+	(59, 81, 139),
+	(44, 113, 142),
+	(33, 144, 141),
+	(39, 173, 129),
+	(92, 200, 99),
+	(170, 220, 50),
+	(253, 231, 37),
+];
+
+/// Golden ratio conjugate, stepping hues so that consecutive indices are
+/// far apart on the color wheel.
+const HUE_STEP: f32 = 0.618_034;
+
+/// Minimal WCAG contrast ratio kept by `Palette::color_on`.
+const MIN_CONTRAST: f32 = 3.0;
+
+#[derive(Clone, Copy, Default, Display, Reflect, PartialEq)]
+pub enum Palette {
+	/// Categorical palette safe for color blind users, repeating after
+	/// seven colors.
+	#[default]
+	OkabeIto,
+	/// Perceptually uniform sequential palette, sampled at golden ratio
+	/// steps so neighboring indices differ.
+	Viridis,
+	/// Fully saturated hues at golden ratio steps, never repeating.
+	Hues,
+}
+
+impl Palette {
+	/// Deterministic color for `index`.
+	pub fn color(&self, index: usize) -> Color {
+		match self {
+			Palette::OkabeIto => rgb_u8(OKABE_ITO[index % OKABE_ITO.len()]),
+			Palette::Viridis => self.sample((index as f32 * HUE_STEP).fract()),
+			Palette::Hues => {
+				Color::hsl(360.0 * (index as f32 * HUE_STEP).fract(), 0.8, 0.5)
+			}
+		}
+	}
+
+	/// Color at `t` in `0..=1` along the palette, where categorical palettes
+	/// are treated as a sequence.
+	pub fn sample(&self, t: f32) -> Color {
+		let samples: &[(u8, u8, u8)] = match self {
+			Palette::OkabeIto => &OKABE_ITO,
+			Palette::Viridis => &VIRIDIS,
+			Palette::Hues => return Color::hsl(360.0 * t.clamp(0.0, 1.0), 0.8, 0.5),
+		};
+		let x = t.clamp(0.0, 1.0) * (samples.len() - 1) as f32;
+		let i = (x.floor() as usize).min(samples.len() - 2);
+		mix(rgb_u8(samples[i]), rgb_u8(samples[i + 1]), x - i as f32)
+	}
+
+	/// `color(index)` darkened or lightened until it has a contrast ratio of
+	/// at least `MIN_CONTRAST` against `background`.
+	pub fn color_on(&self, index: usize, background: Color) -> Color {
+		let color = self.color(index);
+		let target = if relative_luminance(background) > 0.18 {
+			Color::BLACK
+		} else {
+			Color::WHITE
+		};
+		(0..=10)
+			.map(|k| mix(color, target, k as f32 / 10.0))
+			.find(|c| contrast_ratio(*c, background) >= MIN_CONTRAST)
+			.unwrap_or(target)
+	}
+}
+
+/// WCAG relative luminance of the sRGB `color`.
+pub fn relative_luminance(color: Color) -> f32 {
+	let linear = color.as_linear_rgba_f32();
+	0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2]
+}
+
+/// WCAG contrast ratio, from 1 for equal luminance up to 21.
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+	let (la, lb) = (relative_luminance(a), relative_luminance(b));
+	(la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn rgb_u8((r, g, b): (u8, u8, u8)) -> Color {
+	Color::rgb_u8(r, g, b)
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+	let (a, b) = (a.as_rgba_f32(), b.as_rgba_f32());
+	Color::rgba(
+		a[0] + (b[0] - a[0]) * t,
+		a[1] + (b[1] - a[1]) * t,
+		a[2] + (b[2] - a[2]) * t,
+		a[3] + (b[3] - a[3]) * t,
+	)
+}
@@ -0,0 +1,163 @@
+use bevy::{
+	app::{App, Plugin, Startup, Update},
+	core_pipeline::core_2d::Camera2dBundle,
+	ecs::{
+		change_detection::DetectChanges,
+		component::Component,
+		entity::Entity,
+		schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
+		system::{Commands, Query, Res, ResMut},
+	},
+	gizmos::gizmos::Gizmos,
+	input::{keyboard::KeyCode, ButtonInput},
+	render::camera::ClearColor,
+	tasks::{block_on, poll_once, Task},
+};
+
+use crate::{
+	document::Document,
+	geom::arc_poly::{ArcPoly, ArcPolyGenInput, ShrinkError},
+	math::Tolerance,
+	palette::Palette,
+	util::{Cancellation, Warnings},
+};
+
+/// Ordered stages of the geometry pipeline in `Update`. Host apps schedule
+/// their own systems relative to these, e.g. regenerating input shapes in
+/// `Ingest` so they are offset and drawn within the same frame.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RarcSystems {
+	Ingest,
+	Offset,
+	Tessellate,
+	Draw,
+}
+
+/// An offset computing off-thread, e.g. from `ArcPoly::shrunk_async`. Once
+/// finished it is replaced by a `Shrunk` component on the same entity, or
+/// the entity is despawned if the computation failed or was cancelled.
+/// Failures other than cancellation are added to `Warnings`.
+#[derive(Component)]
+pub struct PendingShrink {
+	pub task: Task<Result<Vec<ArcPoly>, ShrinkError>>,
+	pub cancellation: Cancellation,
+}
+
+#[derive(Component)]
+pub struct Shrunk(pub Vec<ArcPoly>);
+
+/// Configures `RarcSystems`, completes `PendingShrink` tasks in `Offset` and
+/// draws the `Document` resource in `Draw`. Also provides the `Warnings`
+/// resource.
+pub struct RarcPlugin;
+
+impl Plugin for RarcPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.init_resource::<Document>()
+			.register_type::<Document>()
+			.init_resource::<Warnings>()
+			.register_type::<Warnings>()
+			.configure_sets(
+				Update,
+				(
+					RarcSystems::Ingest,
+					RarcSystems::Offset,
+					RarcSystems::Tessellate,
+					RarcSystems::Draw,
+				)
+					.chain(),
+			)
+			.add_systems(Update, poll_pending_shrinks.in_set(RarcSystems::Offset))
+			.add_systems(Update, draw_document.in_set(RarcSystems::Draw));
+	}
+}
+
+fn draw_document(mut gizmos: Gizmos, document: Res<Document>) {
+	document.draw(&mut gizmos);
+}
+
+fn poll_pending_shrinks(
+	mut commands: Commands,
+	mut pending: Query<(Entity, &mut PendingShrink)>,
+	mut warnings: ResMut<Warnings>,
+) {
+	for (entity, mut pending_shrink) in pending.iter_mut() {
+		match block_on(poll_once(&mut pending_shrink.task)) {
+			Some(Ok(polys)) => {
+				commands
+					.entity(entity)
+					.remove::<PendingShrink>()
+					.try_insert(Shrunk(polys));
+			}
+			Some(Err(err)) => {
+				if err != ShrinkError::Cancelled {
+					warnings.push(format!("offset failed: {}", err));
+				}
+				commands.entity(entity).despawn();
+			}
+			None => {}
+		}
+	}
+}
+
+/// A camera and a generated shape with its offset, regenerated with the next
+/// or previous seed on the up and down arrow keys. Uses only core bevy, in
+/// contrast to the inspector panels of the full demo.
+pub struct SimpleDemoPlugin;
+
+impl Plugin for SimpleDemoPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.add_plugins(RarcPlugin)
+			.init_resource::<ArcPolyGenInput>()
+			.add_systems(Startup, setup_simple_demo)
+			.add_systems(
+				Update,
+				(simple_demo_hotkeys, simple_demo_generate)
+					.chain()
+					.in_set(RarcSystems::Ingest),
+			);
+	}
+}
+
+fn setup_simple_demo(mut commands: Commands, mut document: ResMut<Document>) {
+	commands.spawn(Camera2dBundle::default());
+	let background = ClearColor::default().0;
+	document.add_layer("input", Palette::OkabeIto.color_on(0, background));
+	document.add_layer("offset", Palette::OkabeIto.color_on(1, background));
+}
+
+fn simple_demo_hotkeys(
+	keys: Res<ButtonInput<KeyCode>>,
+	mut gen_input: ResMut<ArcPolyGenInput>,
+) {
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		gen_input.random_seed = gen_input.random_seed.wrapping_add(1);
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		gen_input.random_seed = gen_input.random_seed.wrapping_sub(1);
+	}
+}
+
+fn simple_demo_generate(
+	gen_input: Res<ArcPolyGenInput>,
+	mut document: ResMut<Document>,
+	mut warnings: ResMut<Warnings>,
+) {
+	if !gen_input.is_changed() || document.layers.len() < 2 {
+		return;
+	}
+	let poly = ArcPoly::from_gen_input(&gen_input);
+	document.clear_layer(0);
+	document.clear_layer(1);
+	match poly.shrunk(gen_input.shrink.max(0.0), &Tolerance::default()) {
+		Ok(offsets) => {
+			for offset in offsets {
+				document.add_shape(1, offset);
+			}
+		}
+		Err(err) => warnings.push(format!("offset failed: {}", err)),
+	}
+	document.add_shape(0, poly);
+}
@@ -0,0 +1,151 @@
+use bevy::math::{Rect, Vec2};
+
+use crate::geom::{arc_poly::ArcPoly, path::ArcPath};
+
+/// Black and white picture of the lines the examples draw with gizmos, for
+/// checking their output without a window or a GPU. Arcs are sampled into
+/// lines at half a pixel, which is about what gizmos do on screen.
+#[derive(Clone, PartialEq)]
+pub struct Raster {
+	pub width: usize,
+	pub height: usize,
+	/// Rows from the top, true where a line was drawn.
+	pub pixels: Vec<bool>,
+	/// The part of the plane that is pictured, stretched to the raster.
+	pub view: Rect,
+}
+
+impl Raster {
+	pub fn new(width: usize, height: usize, view: Rect) -> Raster {
+		Raster { width, height, pixels: vec![false; width * height], view }
+	}
+
+	pub fn draw_poly(&mut self, poly: &ArcPoly) {
+		for i in poly.indices() {
+			self.draw_samples(poly[i].sample(poly.end_of(i), self.tolerance()));
+		}
+	}
+
+	pub fn draw_path(&mut self, path: &ArcPath) {
+		for (i, segment) in path.segments.iter().enumerate() {
+			self.draw_samples(segment.sample(path.end_of(i), self.tolerance()));
+		}
+	}
+
+	/// Line from `a` to `b`, clipped to the view.
+	pub fn draw_line(&mut self, a: Vec2, b: Vec2) {
+		let (a, b) = (self.to_pixel(a), self.to_pixel(b));
+		let steps = (b - a).abs().max_element().ceil().max(1.0) as usize;
+		for k in 0..=steps {
+			let p = a.lerp(b, k as f32 / steps as f32).floor();
+			if p.x >= 0.0
+				&& p.y >= 0.0
+				&& (p.x as usize) < self.width
+				&& (p.y as usize) < self.height
+			{
+				self.pixels[p.y as usize * self.width + p.x as usize] = true;
+			}
+		}
+	}
+
+	/// The raster as a plain PBM image, one text line per row.
+	pub fn to_pbm(&self) -> String {
+		let mut text = format!("P1\n{} {}\n", self.width, self.height);
+		for row in self.pixels.chunks(self.width.max(1)) {
+			let row = row.iter().map(|&on| if on { '1' } else { '0' });
+			text.extend(row);
+			text.push('\n');
+		}
+		text
+	}
+
+	fn draw_samples(&mut self, samples: impl Iterator<Item = Vec2>) {
+		let mut last: Option<Vec2> = None;
+		for p in samples {
+			if let Some(a) = last {
+				self.draw_line(a, p);
+			}
+			last = Some(p);
+		}
+	}
+
+	/// Half the size of a pixel in the plane.
+	fn tolerance(&self) -> f32 {
+		let size = self.view.size();
+		0.5 * (size.x / self.width as f32).min(size.y / self.height as f32)
+	}
+
+	/// Position of `p` in pixels, from the top left corner of the raster.
+	fn to_pixel(&self, p: Vec2) -> Vec2 {
+		let t = (p - self.view.min) / self.view.size();
+		Vec2::new(t.x * self.width as f32, (1.0 - t.y) * self.height as f32)
+	}
+}
+
+/// The raster of `polys` in their overall bounds with a margin of
+/// `margin`, scaled evenly to fit `width` by `height` pixels.
+pub fn rasterize_polys(
+	polys: &[ArcPoly],
+	width: usize,
+	height: usize,
+	margin: f32,
+) -> Raster {
+	let bounds = polys
+		.iter()
+		.map(ArcPoly::bounds)
+		.reduce(|a, b| a.union(b))
+		.unwrap_or_default()
+		.inset(margin);
+	let scale =
+		(bounds.width() / width as f32).max(bounds.height() / height as f32);
+	let half_size = 0.5 * scale * Vec2::new(width as f32, height as f32);
+	let view = Rect::from_center_half_size(bounds.center(), half_size);
+	let mut raster = Raster::new(width, height, view);
+	for poly in polys {
+		raster.draw_poly(poly);
+	}
+	raster
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{math::Tolerance, scenarios};
+
+	/// Compares with the golden file, or writes it when `RARC_BLESS` is set.
+	fn assert_golden(raster: &Raster, name: &str) {
+		let path =
+			format!("{}/tests/golden/{}.pbm", env!("CARGO_MANIFEST_DIR"), name);
+		let found = raster.to_pbm();
+		if std::env::var_os("RARC_BLESS").is_some() {
+			std::fs::write(&path, &found).unwrap();
+			return;
+		}
+		let expected = std::fs::read_to_string(&path).unwrap_or_default();
+		assert!(
+			found == expected,
+			"{} differs from the golden file, rerun with RARC_BLESS=1 to \
+			 update it if intended:\n{}",
+			path,
+			found
+		);
+	}
+
+	#[test]
+	fn lines_are_drawn_up_to_the_edges_of_the_view() {
+		let mut raster = Raster::new(4, 2, Rect::new(0.0, 0.0, 4.0, 2.0));
+		raster.draw_line(Vec2::new(0.5, 0.5), Vec2::new(3.5, 0.5));
+		raster.draw_line(Vec2::new(3.5, 1.5), Vec2::new(10.0, 1.5));
+		assert_eq!(raster.to_pbm(), "P1\n4 2\n0001\n1111\n");
+	}
+
+	#[test]
+	fn default_scenario_matches_golden_file() {
+		let scenario = scenarios::find("default").unwrap();
+		let input = ArcPoly::from_gen_input(&scenario.gen_input());
+		let mut polys =
+			input.shrunk(scenario.shrink, &Tolerance::default()).unwrap();
+		polys.push(input);
+		assert_golden(&rasterize_polys(&polys, 64, 48, 10.0), "default");
+	}
+}
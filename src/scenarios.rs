@@ -0,0 +1,87 @@
+use crate::geom::arc_poly::ArcPolyGenInput;
+
+/// Generator input known to exercise a corner case, named so that tests,
+/// benches, bug reports and the demo's presets can refer to it instead of to
+/// a seed and slider positions.
+#[derive(Clone, Copy)]
+pub struct Scenario {
+	pub name: &'static str,
+	pub description: &'static str,
+	pub random_seed: u32,
+	pub n: usize,
+	pub radius: f32,
+	pub offset_noise: f32,
+	pub bend_max: f32,
+	pub bend_min: f32,
+	pub shrink: f32,
+}
+
+impl Scenario {
+	pub fn gen_input(&self) -> ArcPolyGenInput {
+		let mut gen_input = ArcPolyGenInput {
+			random_seed: self.random_seed,
+			n: self.n,
+			offset_noise: self.offset_noise,
+			bend_max: self.bend_max,
+			bend_min: self.bend_min,
+			shrink: self.shrink,
+			..Default::default()
+		};
+		gen_input.r.set_value(self.radius);
+		gen_input
+	}
+}
+
+/// The registered scenarios. Shrink amounts are placed just off the events
+/// they are about, so a change of the result at them points to a change in
+/// how those events are handled.
+pub const SCENARIOS: &[Scenario] = &[
+	Scenario {
+		name: "default",
+		description: "the demo's initial input",
+		random_seed: 17,
+		n: 13,
+		radius: 250.0,
+		offset_noise: 50.0,
+		bend_max: 0.5,
+		bend_min: 0.02,
+		shrink: 48.5,
+	},
+	Scenario {
+		name: "thin_neck",
+		description: "a single loop shrunk to a neck about to pinch off",
+		random_seed: 0,
+		n: 8,
+		radius: 250.0,
+		offset_noise: 180.0,
+		bend_max: 0.5,
+		bend_min: 0.02,
+		shrink: 63.5,
+	},
+	Scenario {
+		name: "near_tangent_pair",
+		description: "two loops just split from a neck, with nearly tangent arcs",
+		random_seed: 0,
+		n: 8,
+		radius: 250.0,
+		offset_noise: 180.0,
+		bend_max: 0.5,
+		bend_min: 0.02,
+		shrink: 64.0,
+	},
+	Scenario {
+		name: "degenerate_cap",
+		description: "a loop shrunk to a small cap about to vanish",
+		random_seed: 17,
+		n: 13,
+		radius: 250.0,
+		offset_noise: 50.0,
+		bend_max: 0.5,
+		bend_min: 0.02,
+		shrink: 161.5,
+	},
+];
+
+pub fn find(name: &str) -> Option<&'static Scenario> {
+	SCENARIOS.iter().find(|scenario| scenario.name == name)
+}
@@ -0,0 +1,367 @@
+use std::f32::consts::PI;
+
+use bevy::{math::Vec2, prelude::default, reflect::Reflect};
+use derive_more::Display;
+
+use crate::{
+	geom::{
+		arc_poly::{ArcPoly, Convexity, LoopIdx, SegIdx},
+		segment::{ArcAttributes, Bend, SegEnd, Segment},
+	},
+	math::{circle_line_intersection, two_circle_collision, FloatVec2},
+};
+
+const MIN_RELIEF_TURNING: f32 = 0.01;
+
+/// A planar arc of a toolpath annotated with the depth at its ends.
+#[derive(Clone, Copy, Display)]
+#[display(
+	fmt = "ramp_arc({}, {}, {}, {})",
+	segment,
+	end,
+	start_depth,
+	end_depth
+)]
+pub struct RampArc {
+	pub segment: Segment,
+	pub end: Vec2,
+	pub start_depth: f32,
+	pub end_depth: f32,
+}
+
+#[derive(Clone, Copy, Display, Reflect, PartialEq)]
+pub enum DogboneStyle {
+	/// Relief centered on the corner bisector.
+	Bisector,
+	/// Relief centered on the tangent of the incoming segment.
+	TBoneIncoming,
+	/// Relief centered on the tangent of the outgoing segment.
+	TBoneOutgoing,
+}
+
+/// Inserts a relief arc of `tool_radius` at every convex corner of `poly`,
+/// which are the inside corners of a pocket a round tool can not reach.
+/// Corners where the relief does not fit on the adjacent segments are left
+/// untouched.
+pub fn dogbone(
+	poly: &ArcPoly,
+	tool_radius: f32,
+	style: DogboneStyle,
+) -> ArcPoly {
+	let n = poly.segments.len();
+	let mut reliefs: Vec<Option<(Vec2, Segment)>> = vec![None; n];
+	for corner in poly.corners(MIN_RELIEF_TURNING) {
+		if corner.convexity == Convexity::Convex {
+			reliefs[corner.idx.0] =
+				corner_relief(poly, corner.idx, tool_radius, style);
+		}
+	}
+	let mut res = ArcPoly::default();
+	for (segment, relief) in poly.segments.iter().zip(reliefs) {
+		match relief {
+			Some((p_out, relief)) => {
+				res.segments.push(relief);
+				res.segments.push(Segment { initial: p_out, ..*segment });
+			}
+			None => res.segments.push(*segment),
+		}
+	}
+	res
+}
+
+/// The relief segment at the corner starting segment `j` together with the
+/// new initial point of segment `j`.
+fn corner_relief(
+	poly: &ArcPoly,
+	j: SegIdx,
+	tool_radius: f32,
+	style: DogboneStyle,
+) -> Option<(Vec2, Segment)> {
+	let n = poly.segments.len();
+	let (a, b, c) = (&poly[j.prev(n)], &poly[j], &poly[j.next(n)]);
+	let place = b.initial;
+	let (a_end, b_end) = (poly.end_of(j.prev(n)), poly.end_of(j));
+	let in_dir = a.tangent_at(a_end, &place);
+	let out_dir = b.tangent_at(b_end, &place);
+	let center = place
+		+ tool_radius
+			* match style {
+				DogboneStyle::Bisector => (out_dir - in_dir).normalize(),
+				DogboneStyle::TBoneIncoming => -in_dir,
+				DogboneStyle::TBoneOutgoing => out_dir,
+			};
+	let relief = FloatVec2 { f: tool_radius, v: center };
+	let mut p_in = far_collision(&relief, a, &place, &place);
+	let mut p_out = far_collision(&relief, b, &c.initial, &place);
+	// The t-bone styles touch one of the segments at the corner itself.
+	if (p_in - place).dot(in_dir) >= 0.0 {
+		p_in = place;
+	}
+	if (p_out - place).dot(out_dir) <= 0.0 {
+		p_out = place;
+	}
+	let on_segment = |s: &Segment, end: SegEnd, p: &Vec2| {
+		*p == s.initial || (0.0..=1.0).contains(&s.fraction_at(end, p))
+	};
+	if !on_segment(a, a_end, &p_in) || !on_segment(b, b_end, &p_out) {
+		return None;
+	}
+	let segment = Segment {
+		initial: p_in,
+		center,
+		bend: Bend::Outward,
+		attributes: b.attributes,
+	};
+	Some((p_out, segment))
+}
+
+/// The collision of `relief` with the circle or line of `segment` farthest
+/// from `place`, or `place` itself when they only touch there.
+fn far_collision(
+	relief: &FloatVec2,
+	segment: &Segment,
+	next_initial: &Vec2,
+	place: &Vec2,
+) -> Vec2 {
+	let collisions = match segment.bend {
+		Bend::Straight => {
+			let dir = *next_initial - segment.initial;
+			circle_line_intersection(relief, &segment.initial, &dir)
+				.into_iter()
+				.map(|t| segment.initial + t * dir)
+				.collect()
+		}
+		_ => two_circle_collision(relief, &segment.circle()),
+	};
+	collisions
+		.into_iter()
+		.max_by(|p, q| {
+			(*p - *place).length_squared().total_cmp(&(*q - *place).length_squared())
+		})
+		.unwrap_or(*place)
+}
+
+/// Counter-clockwise helical entry around `center`, projected to half circle
+/// arcs that descend at most `depth_per_turn` per revolution from
+/// `start_depth` to `end_depth`, followed by one flat revolution at
+/// `end_depth` to clean up the bottom of the bore. A `depth_per_turn` which
+/// is not positive yields no arcs.
+pub fn helical_entry(
+	center: Vec2,
+	radius: f32,
+	start_depth: f32,
+	end_depth: f32,
+	depth_per_turn: f32,
+) -> Vec<RampArc> {
+	if depth_per_turn.is_nan() || depth_per_turn <= 0.0 {
+		return vec![];
+	}
+	let half_step = 0.5 * depth_per_turn;
+	let descent = end_depth - start_depth;
+	let halves = (descent.abs() / half_step).ceil() as usize;
+	let mut arcs: Vec<RampArc> = vec![];
+	let mut depth = start_depth;
+	for i in 0..halves + 2 {
+		let next_depth = if i + 1 >= halves {
+			end_depth
+		} else {
+			start_depth + descent * (i + 1) as f32 / halves as f32
+		};
+		let initial = center + radius * Vec2::from_angle(PI * i as f32);
+		arcs.push(RampArc {
+			segment: Segment {
+				initial,
+				center,
+				bend: Bend::Outward,
+				attributes: default(),
+			},
+			end: 2.0 * center - initial,
+			start_depth: depth,
+			end_depth: next_depth,
+		});
+		depth = next_depth;
+	}
+	arcs
+}
+
+/// Visiting order of closed `paths` entered at their first segment, starting
+/// from `start`. Paths contained in another path are visited before it, so
+/// inner features are cut while the surrounding material still holds the
+/// part. Nearest neighbor construction followed by 2-opt improvement.
+pub fn order_paths(paths: &[ArcPoly], start: Vec2) -> Vec<LoopIdx> {
+	let n = paths.len();
+	let entry =
+		|i: usize| paths[i].segments.first().map(|s| s.initial).unwrap_or(start);
+	let inner: Vec<Vec<usize>> = (0..n)
+		.map(|j| {
+			(0..n).filter(|&i| i != j && paths[j].contains_point(&entry(i))).collect()
+		})
+		.collect();
+
+	let mut order: Vec<usize> = vec![];
+	let mut visited = vec![false; n];
+	let mut place = start;
+	for _ in 0..n {
+		let next = (0..n)
+			.filter(|&j| !visited[j] && inner[j].iter().all(|&i| visited[i]))
+			.min_by(|&i, &j| {
+				(entry(i) - place)
+					.length_squared()
+					.total_cmp(&(entry(j) - place).length_squared())
+			})
+			// Mutual containment only happens for overlapping paths.
+			.unwrap_or_else(|| (0..n).find(|&j| !visited[j]).unwrap());
+		visited[next] = true;
+		order.push(next);
+		place = entry(next);
+	}
+
+	// Reversing `order[i..=j]` only changes the moves into and out of the
+	// reversed run, and only breaks containment within it, where every path
+	// comes after those inside it so far.
+	let distance = |a: Vec2, b: Vec2| (a - b).length();
+	let mut position = vec![0; n];
+	for (k, &i) in order.iter().enumerate() {
+		position[i] = k;
+	}
+	let mut improved = true;
+	while improved {
+		improved = false;
+		for i in 0..n {
+			for j in i + 1..n {
+				let before = if i == 0 { start } else { entry(order[i - 1]) };
+				let (first, last) = (entry(order[i]), entry(order[j]));
+				let (mut old, mut new) =
+					(distance(before, first), distance(before, last));
+				if let Some(&after) = order.get(j + 1) {
+					old += distance(last, entry(after));
+					new += distance(first, entry(after));
+				}
+				if new >= old - f32::EPSILON * old {
+					continue;
+				}
+				let nested = order[i..=j]
+					.iter()
+					.any(|&k| inner[k].iter().any(|&m| position[m] >= i));
+				if nested {
+					continue;
+				}
+				order[i..=j].reverse();
+				for k in i..=j {
+					position[order[k]] = k;
+				}
+				improved = true;
+			}
+		}
+	}
+	order.into_iter().map(LoopIdx).collect()
+}
+
+/// G-code cutting `loops` in turn in the XY plane, each entered by a rapid
+/// move to its start. Arcs become `G2`/`G3` moves with their center given
+/// relative to their start, straight segments `G1` moves. The feed and power
+/// of the segments are given as `F` and `S` words where they change, and
+/// their layer as a comment where it changes.
+pub fn gcode(loops: &[ArcPoly]) -> String {
+	let mut text = String::from("G17 G90\n");
+	let (mut feed, mut power, mut layer) = (None, None, None);
+	for poly in loops {
+		let Some(first) = poly.segments.first() else {
+			continue;
+		};
+		text += &format!("G0 X{} Y{}\n", first.initial.x, first.initial.y);
+		for i in poly.indices() {
+			let segment = &poly[i];
+			let ArcAttributes { layer: l, feed: f, power: p } = segment.attributes;
+			if layer != Some(l) {
+				layer = Some(l);
+				text += &format!("(layer {})\n", l);
+			}
+			let end = segment.end_point(poly.end_of(i));
+			text += &match segment.bend {
+				Bend::Straight => format!("G1 X{} Y{}", end.x, end.y),
+				bend => {
+					let code = if bend == Bend::Outward { "G3" } else { "G2" };
+					let offset = segment.center - segment.initial;
+					format!("{} X{} Y{} I{} J{}", code, end.x, end.y, offset.x, offset.y)
+				}
+			};
+			if let Some(value) = f.filter(|_| f != feed) {
+				feed = f;
+				text += &format!(" F{}", value);
+			}
+			if let Some(value) = p.filter(|_| p != power) {
+				power = p;
+				text += &format!(" S{}", value);
+			}
+			text += "\n";
+		}
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use bevy::math::Rect;
+
+	use super::*;
+
+	#[test]
+	fn helical_entry_rejects_non_positive_depth_per_turn() {
+		for depth_per_turn in [0.0, -1.0, f32::NAN] {
+			assert!(
+				helical_entry(Vec2::ZERO, 5.0, 0.0, -10.0, depth_per_turn).is_empty()
+			);
+		}
+		let arcs = helical_entry(Vec2::ZERO, 5.0, 0.0, -10.0, 2.0);
+		assert_eq!(arcs.len(), 12);
+		assert!(arcs.iter().all(|arc| arc.start_depth - arc.end_depth <= 1.0));
+		assert_eq!(arcs.last().unwrap().end_depth, -10.0);
+	}
+
+	#[test]
+	fn order_paths_shortens_the_nearest_neighbor_tour() {
+		let circle = |x: f32| ArcPoly::circle(Vec2::new(x, 0.0), 0.1);
+		let paths = [circle(1.0), circle(-2.0), circle(4.0)];
+		assert_eq!(
+			order_paths(&paths, Vec2::ZERO),
+			[LoopIdx(1), LoopIdx(0), LoopIdx(2)]
+		);
+	}
+
+	#[test]
+	fn order_paths_cuts_inner_paths_first() {
+		let paths = [
+			ArcPoly::circle(Vec2::new(-5.0, 0.0), 20.0),
+			ArcPoly::circle(Vec2::new(5.0, 0.0), 1.0),
+			ArcPoly::circle(Vec2::new(-5.0, 0.0), 1.0),
+			ArcPoly::circle(Vec2::new(40.0, 0.0), 1.0),
+		];
+		let order = order_paths(&paths, Vec2::new(50.0, 0.0));
+		let position = |i| order.iter().position(|&LoopIdx(j)| j == i).unwrap();
+		assert_eq!(order.len(), paths.len());
+		assert!(position(1) < position(0) && position(2) < position(0));
+	}
+
+	#[test]
+	fn gcode_gives_feed_and_power_where_they_change() {
+		let mut square = ArcPoly::rectangle(Rect::new(0.0, 0.0, 10.0, 10.0));
+		let cut = ArcAttributes { layer: 1, feed: Some(1200.0), power: Some(0.5) };
+		for segment in square.segments.iter_mut() {
+			segment.attributes = cut;
+		}
+		square.segments[2].attributes.feed = Some(600.0);
+		let circle = ArcPoly::circle(Vec2::new(20.0, 0.0), 2.0);
+		let expected = "G17 G90\n\
+			G0 X0 Y0\n\
+			(layer 1)\n\
+			G1 X10 Y0 F1200 S0.5\n\
+			G1 X10 Y10\n\
+			G1 X0 Y10 F600\n\
+			G1 X0 Y0 F1200\n\
+			G0 X22 Y0\n\
+			(layer 0)\n\
+			G3 X22 Y0 I-2 J0\n";
+		assert_eq!(gcode(&[square, circle]), expected);
+	}
+}
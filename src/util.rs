@@ -1,6 +1,19 @@
+use std::{
+	fmt::{Display, Formatter, Result},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
 use bevy::{
-	ecs::system::Resource, gizmos::gizmos::Gizmos, reflect::Reflect,
-	render::color::Color,
+	ecs::system::Resource,
+	gizmos::gizmos::Gizmos,
+	log::warn,
+	math::Rect,
+	reflect::Reflect,
+	render::{camera::OrthographicProjection, color::Color},
+	transform::components::Transform,
 };
 
 use crate::math::FloatVec2;
@@ -20,3 +33,112 @@ impl Default for TimeResource {
 pub fn gizmo_circle(gizmos: &mut Gizmos, circle: FloatVec2, color: Color) {
 	gizmos.circle_2d(circle.v, circle.f, color);
 }
+
+/// Centers the camera on `bounds` and scales the projection so that they fit
+/// its viewport with `margin` world units to spare on every side. The
+/// viewport size is taken from `projection.area`, so this must run after the
+/// camera has been updated for the window.
+pub fn fit_camera_to(
+	bounds: Rect,
+	projection: &mut OrthographicProjection,
+	transform: &mut Transform,
+	margin: f32,
+) {
+	let viewport = projection.area.size() / projection.scale;
+	let size = bounds.inset(margin).size();
+	if bounds.is_empty() || viewport.min_element() <= 0.0 {
+		return;
+	}
+	projection.scale = (size / viewport).max_element();
+	let center = bounds.center();
+	transform.translation.x = center.x;
+	transform.translation.y = center.y;
+}
+
+/// Shared flag for aborting long running computations from another thread.
+#[derive(Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// Notes on degraded results, e.g. geometry dropped by a lenient operation.
+/// Each warning is also logged under the `rarc` target when pushed.
+#[derive(Reflect, Resource, Default)]
+pub struct Warnings(pub Vec<String>);
+
+impl Warnings {
+	pub fn push(&mut self, warning: String) {
+		warn!(target: "rarc", "{}", warning);
+		self.0.push(warning);
+	}
+
+	pub fn clear(&mut self) {
+		self.0.clear();
+	}
+}
+
+/// Scalar edited through `position` in `0..=1`, which maps to `min..=max`
+/// linearly or, when `logarithmic`, exponentially so that small and large
+/// values can be explored with the same slider.
+#[derive(Clone, Copy, Reflect, PartialEq)]
+pub struct Param {
+	pub position: f32,
+	pub min: f32,
+	pub max: f32,
+	pub logarithmic: bool,
+	#[reflect(ignore)]
+	pub unit: &'static str,
+}
+
+impl Display for Param {
+	fn fmt(&self, f: &mut Formatter) -> Result {
+		write!(f, "{} {}", self.value(), self.unit)
+	}
+}
+
+impl Param {
+	pub fn linear(value: f32, min: f32, max: f32, unit: &'static str) -> Self {
+		let mut param = Param { position: 0.0, min, max, logarithmic: false, unit };
+		param.set_value(value);
+		param
+	}
+
+	/// Requires `0 < min < max`.
+	pub fn logarithmic(
+		value: f32,
+		min: f32,
+		max: f32,
+		unit: &'static str,
+	) -> Self {
+		let mut param = Param { position: 0.0, min, max, logarithmic: true, unit };
+		param.set_value(value);
+		param
+	}
+
+	pub fn value(&self) -> f32 {
+		let t = self.position.clamp(0.0, 1.0);
+		if self.logarithmic {
+			self.min * (self.max / self.min).powf(t)
+		} else {
+			self.min + (self.max - self.min) * t
+		}
+	}
+
+	/// Moves `position` to `value`, clamped to `min..=max`.
+	pub fn set_value(&mut self, value: f32) {
+		let value = value.clamp(self.min, self.max);
+		self.position = if self.logarithmic {
+			(value / self.min).ln() / (self.max / self.min).ln()
+		} else {
+			(value - self.min) / (self.max - self.min)
+		};
+	}
+}